@@ -0,0 +1,136 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A classic anomaly which makes a history non-serializable, ordered from the
+/// weakest isolation level it violates to the strongest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AnomalyKind {
+    /// A transaction reads a value only an aborted transaction wrote.
+    AbortedRead,
+    /// A transaction reads a value its writer overwrote before committing.
+    IntermediateRead,
+    /// Transactions read each other's writes in a cycle, which violates read
+    /// committed.
+    CircularInformationFlow,
+    /// A transaction observes some writes of another transaction but misses
+    /// the others.
+    ReadSkew,
+    /// A transaction observes the effect of a write but misses a write which
+    /// causally precedes it.
+    CausalityViolation,
+    /// Two transactions observe concurrent writes in different orders.
+    LongFork,
+    /// Two transactions read the same version and both overwrite it, which
+    /// snapshot isolation forbids.
+    LostUpdate,
+    /// Two transactions read overlapping data and update disjoint parts of
+    /// it, which only serializability forbids.
+    WriteSkew,
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AnomalyKind::AbortedRead => "aborted read",
+            AnomalyKind::IntermediateRead => "intermediate read",
+            AnomalyKind::CircularInformationFlow => "circular information flow",
+            AnomalyKind::ReadSkew => "read skew",
+            AnomalyKind::CausalityViolation => "causality violation",
+            AnomalyKind::LongFork => "long fork",
+            AnomalyKind::LostUpdate => "lost update",
+            AnomalyKind::WriteSkew => "write skew",
+        };
+        f.write_str(name)
+    }
+}
+
+/// An isolation level in the chain checked by `History::max_level`, from
+/// the strongest to the weakest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IsolationLevel {
+    Serializable,
+    SnapshotIsolation,
+    Prefix,
+    ReadCommitted,
+    ReadUncommitted,
+    /// The history violates even read uncommitted.
+    None,
+}
+
+impl fmt::Display for IsolationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            IsolationLevel::Serializable => "serializable",
+            IsolationLevel::SnapshotIsolation => "snapshot isolation",
+            IsolationLevel::Prefix => "prefix consistency",
+            IsolationLevel::ReadCommitted => "read committed",
+            IsolationLevel::ReadUncommitted => "read uncommitted",
+            IsolationLevel::None => "none",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The verdict of every isolation level on one history, as returned by
+/// `History::check_hierarchy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HierarchyReport {
+    pub ser: bool,
+    pub si: bool,
+    pub gsi: bool,
+    pub prefix: bool,
+    pub causal: bool,
+    pub cursor_stability: bool,
+    pub mav: bool,
+    pub read_committed: bool,
+    pub read_uncommitted: bool,
+}
+
+impl HierarchyReport {
+    /// Every level implies the ones it is paired with: serializability
+    /// implies snapshot isolation, which implies both generalized snapshot
+    /// isolation and prefix consistency, and so on down to read uncommitted.
+    pub const IMPLICATIONS: [(&'static str, &'static str); 11] = [
+        ("ser", "si"),
+        ("si", "gsi"),
+        ("si", "prefix"),
+        ("gsi", "cursor_stability"),
+        ("gsi", "mav"),
+        ("prefix", "causal"),
+        ("causal", "mav"),
+        ("cursor_stability", "read_committed"),
+        ("mav", "read_committed"),
+        ("ser", "cursor_stability"),
+        ("read_committed", "read_uncommitted"),
+    ];
+
+    fn holds(&self, level: &str) -> bool {
+        match level {
+            "ser" => self.ser,
+            "si" => self.si,
+            "gsi" => self.gsi,
+            "prefix" => self.prefix,
+            "causal" => self.causal,
+            "cursor_stability" => self.cursor_stability,
+            "mav" => self.mav,
+            "read_committed" => self.read_committed,
+            "read_uncommitted" => self.read_uncommitted,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Lists the implications the verdicts break, as pairs of a level which
+    /// holds and a weaker one which does not. Any of them means a checker
+    /// is wrong.
+    pub fn violations(&self) -> Vec<(&'static str, &'static str)> {
+        Self::IMPLICATIONS
+            .iter()
+            .filter(|(stronger, weaker)| self.holds(stronger) && !self.holds(weaker))
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_monotonic(&self) -> bool {
+        self.violations().is_empty()
+    }
+}
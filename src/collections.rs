@@ -0,0 +1,7 @@
+// Without `std` the hash maps come from hashbrown, everything else the
+// checker needs is in `alloc`.
+pub use alloc::collections::{BTreeMap, BTreeSet};
+#[cfg(not(feature = "std"))]
+pub use hashbrown::{HashMap, HashSet};
+#[cfg(feature = "std")]
+pub use std::collections::{HashMap, HashSet};
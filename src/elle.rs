@@ -0,0 +1,296 @@
+//! Reads histories recorded by Jepsen in the EDN format checked by Elle.
+//!
+//! Only completed transactions are read: every entry with `:f :txn` and
+//! `:type :ok` becomes a committed transaction and every one with
+//! `:type :fail` an aborted one, while `:invoke` and `:info` entries and
+//! entries of other processes like the nemesis are skipped. The transactions
+//! of each integer `:process` form a client, and clients are ordered by
+//! process. The micro-operations are `[:r k v]`, `[:w k v]`,
+//! `[:append k v]` and `[:r k [v ...]]`. Reading `nil` reads the initial
+//! value, or the empty list if `k` is appended to somewhere in the history.
+
+use crate::collections::{BTreeMap, HashSet};
+use crate::error::ElleError;
+use crate::transaction::{Append, History, Key, Op, Read, Transaction, Value};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+#[derive(Debug)]
+enum Edn {
+    Nil,
+    Keyword(String),
+    Str(String),
+    // numbers, booleans and symbols
+    Atom(String),
+    // lists, vectors and sets
+    Seq(Vec<Edn>),
+    Map(Vec<(Edn, Edn)>),
+}
+
+impl Edn {
+    fn get(&self, key: &str) -> Option<&Edn> {
+        match self {
+            Edn::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| matches!(k, Edn::Keyword(k) if k == key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Edn::Keyword(k) if k == keyword)
+    }
+
+    // keys are compared by their printed form, whatever their EDN type
+    fn key_text(&self) -> String {
+        match self {
+            Edn::Str(s) | Edn::Atom(s) | Edn::Keyword(s) => s.clone(),
+            form => format!("{:?}", form),
+        }
+    }
+}
+
+struct Reader<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, message: &str) -> ElleError {
+        ElleError::Syntax {
+            offset: self.pos,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == ';' {
+                self.pos += self.input[self.pos..].find('\n').unwrap_or(self.input.len() - self.pos);
+            } else if c.is_whitespace() || c == ',' {
+                self.pos += c.len_utf8();
+            } else {
+                break;
+            }
+        }
+    }
+
+    // reads forms until `close`, which is consumed
+    fn read_seq(&mut self, close: char) -> Result<Vec<Edn>, ElleError> {
+        let mut forms = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(forms);
+                }
+                Some(_) => forms.push(self.read()?),
+                None => return Err(self.error("unclosed collection")),
+            }
+        }
+    }
+
+    fn read_str(&mut self) -> Result<Edn, ElleError> {
+        let mut s = String::new();
+        let mut chars = self.input[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(Edn::Str(s));
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => s.push('\n'),
+                    Some((_, 't')) => s.push('\t'),
+                    Some((_, 'r')) => s.push('\r'),
+                    Some((_, c)) => s.push(c),
+                    None => break,
+                },
+                c => s.push(c),
+            }
+        }
+
+        Err(self.error("unterminated string"))
+    }
+
+    fn read(&mut self) -> Result<Edn, ElleError> {
+        self.skip_whitespace();
+        let c = self.peek().ok_or_else(|| self.error("unexpected end of input"))?;
+        self.pos += c.len_utf8();
+
+        match c {
+            '[' => self.read_seq(']').map(Edn::Seq),
+            '(' => self.read_seq(')').map(Edn::Seq),
+            '#' if self.peek() == Some('{') => {
+                self.pos += 1;
+                self.read_seq('}').map(Edn::Seq)
+            }
+            '{' => {
+                let forms = self.read_seq('}')?;
+                if forms.len() % 2 != 0 {
+                    return Err(self.error("map with an odd number of forms"));
+                }
+                let mut forms = forms.into_iter();
+                let mut entries = Vec::new();
+                while let (Some(k), Some(v)) = (forms.next(), forms.next()) {
+                    entries.push((k, v));
+                }
+                Ok(Edn::Map(entries))
+            }
+            '"' => self.read_str(),
+            ']' | ')' | '}' => {
+                self.pos -= 1;
+                Err(self.error("unexpected closing delimiter"))
+            }
+            _ => {
+                let start = self.pos - c.len_utf8();
+                let end = self.input[start..]
+                    .find(|c: char| c.is_whitespace() || ",;\"[](){}".contains(c))
+                    .map_or(self.input.len(), |len| start + len);
+                self.pos = end;
+
+                let atom = &self.input[start..end];
+                Ok(match atom {
+                    "nil" => Edn::Nil,
+                    _ => match atom.strip_prefix(':') {
+                        Some(keyword) => Edn::Keyword(keyword.to_string()),
+                        None => Edn::Atom(atom.to_string()),
+                    },
+                })
+            }
+        }
+    }
+}
+
+fn parse<T: FromStr>(form: &Edn, index: usize) -> Result<T, ElleError> {
+    let text = match form {
+        Edn::Str(s) | Edn::Atom(s) | Edn::Keyword(s) => s,
+        _ => {
+            return Err(ElleError::Entry {
+                index,
+                message: format!("expected a key or a value, found {:?}", form),
+            })
+        }
+    };
+
+    text.parse().map_err(|_| ElleError::Entry {
+        index,
+        message: format!("cannot parse {:?}", text),
+    })
+}
+
+/// Converts the completed transactions of an EDN history into a `History`.
+/// The entries are either top-level forms or the elements of a top-level
+/// vector, and keys and values are parsed from their printed form.
+pub fn parse_history<K, V>(input: &str) -> Result<History<K, V>, ElleError>
+where
+    K: Key + FromStr,
+    V: Value + FromStr,
+{
+    let mut reader = Reader { input, pos: 0 };
+    let mut entries = Vec::new();
+    loop {
+        reader.skip_whitespace();
+        if reader.peek().is_none() {
+            break;
+        }
+        match reader.read()? {
+            Edn::Seq(forms) => entries.extend(forms),
+            form => entries.push(form),
+        }
+    }
+
+    let mut txns = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        if !matches!(entry.get("f"), Some(f) if f.is_keyword("txn")) {
+            continue;
+        }
+        let aborted = match entry.get("type") {
+            Some(t) if t.is_keyword("ok") => false,
+            Some(t) if t.is_keyword("fail") => true,
+            _ => continue,
+        };
+        let process: i64 = match entry.get("process") {
+            Some(Edn::Atom(process)) => match process.parse() {
+                Ok(process) => process,
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+        let mops = match entry.get("value") {
+            Some(Edn::Seq(mops)) => mops,
+            _ => {
+                return Err(ElleError::Entry {
+                    index,
+                    message: "the value of a transaction is not a sequence".to_string(),
+                })
+            }
+        };
+
+        txns.push((index, process, aborted, mops));
+    }
+
+    // which keys hold lists decides what reading nil means
+    let mut lists = HashSet::new();
+    for (_, _, _, mops) in txns.iter() {
+        for mop in mops.iter() {
+            if let Edn::Seq(mop) = mop {
+                if let [f, k, _] = &mop[..] {
+                    if f.is_keyword("append") {
+                        lists.insert(k.key_text());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut clients: BTreeMap<i64, Vec<Transaction<K, V>>> = BTreeMap::new();
+    for (index, process, aborted, mops) in txns.into_iter() {
+        let mut ops = Vec::new();
+        for mop in mops.iter() {
+            let (f, k, v) = match mop {
+                Edn::Seq(mop) if mop.len() == 3 => (&mop[0], &mop[1], &mop[2]),
+                _ => {
+                    return Err(ElleError::Entry {
+                        index,
+                        message: format!("malformed micro-operation {:?}", mop),
+                    })
+                }
+            };
+            let key: K = parse(k, index)?;
+
+            let op = match (f, v) {
+                (Edn::Keyword(f), Edn::Nil) if f == "r" && lists.contains(&k.key_text()) => {
+                    Op::Read(Read::new(key, Vec::new()))
+                }
+                (Edn::Keyword(f), Edn::Nil) if f == "r" => Op::get(key, V::initial_value()),
+                (Edn::Keyword(f), Edn::Seq(vals)) if f == "r" => Op::Read(Read::new(
+                    key,
+                    vals.iter().map(|v| parse(v, index)).collect::<Result<_, _>>()?,
+                )),
+                (Edn::Keyword(f), v) if f == "r" => Op::get(key, parse(v, index)?),
+                (Edn::Keyword(f), v) if f == "w" => Op::set(key, parse(v, index)?),
+                (Edn::Keyword(f), v) if f == "append" => Op::Append(Append::new(key, parse(v, index)?)),
+                _ => {
+                    return Err(ElleError::Entry {
+                        index,
+                        message: format!("unknown micro-operation {:?}", f),
+                    })
+                }
+            };
+            ops.push(op);
+        }
+
+        clients.entry(process).or_default().push(Transaction { ops, aborted, time: None, interval: None });
+    }
+
+    Ok(History::new(clients.into_values()))
+}
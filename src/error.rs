@@ -0,0 +1,165 @@
+use crate::transaction::{Key, Value};
+use core::error::Error;
+use core::fmt;
+#[cfg(feature = "elle")]
+use alloc::string::String;
+
+/// A history which cannot be checked meaningfully.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HistoryError<K: Key, V: Value> {
+    /// Transaction `transaction` of client `client` reads a value no
+    /// transaction ever wrote.
+    UnwrittenRead {
+        client: usize,
+        transaction: usize,
+        key: K,
+        val: V,
+    },
+    /// Transaction `transaction` of client `client` observes `key` holding
+    /// `V::initial_value()` in a range read, which only lists present keys.
+    InitialRangeRead { client: usize, transaction: usize, key: K },
+    /// Transaction `transaction` of client `client` reads a list containing
+    /// an element no transaction ever appended.
+    UnwrittenElement {
+        client: usize,
+        transaction: usize,
+        key: K,
+        val: V,
+    },
+    /// Transaction `transaction` of client `client` reads `key = val` after
+    /// writing `written` to it itself.
+    OwnWriteMismatch {
+        client: usize,
+        transaction: usize,
+        key: K,
+        written: V,
+        val: V,
+    },
+    /// Transaction `transaction` of client `client` reads `key` as absent,
+    /// but no transaction ever deletes it.
+    UndeletedRead { client: usize, transaction: usize, key: K },
+    /// Transaction `transaction` of client `client` reads a value of `key`
+    /// after deleting it itself, or reads it as absent after setting it.
+    OwnDeleteMismatch { client: usize, transaction: usize, key: K },
+    /// Transaction `transaction` of client `client` reads `key`
+    /// inconsistently with its own earlier writes otherwise, e.g. a list
+    /// without an element it appended.
+    InconsistentRead { client: usize, transaction: usize, key: K },
+    /// `key` is used both as a register and as a list.
+    MixedKey { key: K },
+}
+
+impl<K: Key, V: Value> fmt::Display for HistoryError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::UnwrittenRead {
+                client,
+                transaction,
+                key,
+                val,
+            } => write!(
+                f,
+                "transaction {} of client {} reads {:?} = {:?}, which is never written",
+                transaction, client, key, val
+            ),
+            HistoryError::InitialRangeRead {
+                client,
+                transaction,
+                key,
+            } => write!(
+                f,
+                "transaction {} of client {} observes {:?} holding the initial value in a range read",
+                transaction, client, key
+            ),
+            HistoryError::UnwrittenElement {
+                client,
+                transaction,
+                key,
+                val,
+            } => write!(
+                f,
+                "transaction {} of client {} reads {:?} from list {:?}, which is never appended",
+                transaction, client, val, key
+            ),
+            HistoryError::OwnWriteMismatch {
+                client,
+                transaction,
+                key,
+                written,
+                val,
+            } => write!(
+                f,
+                "transaction {} of client {} reads {:?} = {:?} after writing {:?}",
+                transaction, client, key, val, written
+            ),
+            HistoryError::UndeletedRead {
+                client,
+                transaction,
+                key,
+            } => write!(
+                f,
+                "transaction {} of client {} reads {:?} as absent, but it is never deleted",
+                transaction, client, key
+            ),
+            HistoryError::OwnDeleteMismatch {
+                client,
+                transaction,
+                key,
+            } => write!(
+                f,
+                "transaction {} of client {} reads {:?} inconsistently with its own delete",
+                transaction, client, key
+            ),
+            HistoryError::InconsistentRead {
+                client,
+                transaction,
+                key,
+            } => write!(
+                f,
+                "transaction {} of client {} reads {:?} inconsistently with its own writes",
+                transaction, client, key
+            ),
+            HistoryError::MixedKey { key } => write!(f, "{:?} is used both as a register and as a list", key),
+        }
+    }
+}
+
+impl<K: Key, V: Value> Error for HistoryError<K, V> {}
+
+/// The search for a serial order explored more than `max_nodes` states
+/// without reaching a verdict, as reported by `History::ser_check_bounded`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TooLarge {
+    pub max_nodes: u64,
+}
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no verdict after exploring {} search states", self.max_nodes)
+    }
+}
+
+impl Error for TooLarge {}
+
+/// An EDN history which cannot be read.
+#[cfg(feature = "elle")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ElleError {
+    /// The input is not EDN at byte `offset`.
+    Syntax { offset: usize, message: String },
+    /// The entry at `index` is not a transaction the parser understands.
+    Entry { index: usize, message: String },
+}
+
+#[cfg(feature = "elle")]
+impl fmt::Display for ElleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElleError::Syntax { offset, message } => write!(f, "{} at byte {}", message, offset),
+            ElleError::Entry { index, message } => write!(f, "entry {}: {}", index, message),
+        }
+    }
+}
+
+#[cfg(feature = "elle")]
+impl Error for ElleError {}
@@ -0,0 +1,109 @@
+//! Generates random histories to exercise the checkers.
+//!
+//! The histories come from simulating a store: clients run their
+//! transactions interleaved, every transaction reads the snapshot taken when
+//! it started along with its own writes, and commits install its writes.
+//! Now and then a read observes the latest committed value instead of the
+//! snapshot, as under read committed, and concurrent writes are never
+//! rejected, so lost updates, write skew and fractured reads all happen
+//! while every read still observes a committed write.
+
+use crate::collections::HashMap;
+use crate::transaction::{History, Op, Transaction};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A small deterministic generator of pseudo-random numbers, so histories
+/// can be reproduced from a seed.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    /// Returns a number in `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % bound as u64) as usize
+    }
+}
+
+// a transaction which has started but not committed yet
+struct Running {
+    snapshot: Vec<usize>,
+    written: HashMap<usize, usize>,
+    ops: Vec<Op<usize, usize>>,
+    remaining: usize,
+}
+
+/// Generates a history of `clients` clients running `transactions`
+/// transactions each, with between 1 and `ops` operations per transaction
+/// over the keys `0..keys`. Every write writes a value of its own, so a read
+/// tells which transaction it observes, and the initial value is 0. Keys
+/// stay clear of the guards of the isolation checks while `keys` is at most
+/// 1024.
+pub fn random_history(
+    rng: &mut Rng,
+    clients: usize,
+    transactions: usize,
+    keys: usize,
+    ops: usize,
+) -> History<usize, usize> {
+    let mut committed = vec![0; keys];
+    let mut next_val = 1;
+    let mut running: Vec<Option<Running>> = (0..clients).map(|_| None).collect();
+    let mut history = vec![Vec::new(); clients];
+
+    loop {
+        let active: Vec<usize> = (0..clients)
+            .filter(|c| running[*c].is_some() || history[*c].len() < transactions)
+            .collect();
+        if active.is_empty() {
+            break;
+        }
+        let c = active[rng.below(active.len())];
+
+        match running[c].take() {
+            None => {
+                running[c] = Some(Running {
+                    snapshot: committed.clone(),
+                    written: HashMap::new(),
+                    ops: Vec::new(),
+                    remaining: 1 + rng.below(ops),
+                });
+            }
+            Some(mut t) if t.remaining > 0 => {
+                let key = rng.below(keys);
+                if rng.below(2) == 0 {
+                    let val = match t.written.get(&key) {
+                        Some(val) => *val,
+                        None if rng.below(8) == 0 => committed[key],
+                        None => t.snapshot[key],
+                    };
+                    t.ops.push(Op::get(key, val));
+                } else {
+                    t.written.insert(key, next_val);
+                    t.ops.push(Op::set(key, next_val));
+                    next_val += 1;
+                }
+                t.remaining -= 1;
+                running[c] = Some(t);
+            }
+            Some(t) => {
+                for (key, val) in t.written.into_iter() {
+                    committed[key] = val;
+                }
+                history[c].push(Transaction {
+                    ops: t.ops,
+                    aborted: false,
+                    time: None,
+                    interval: None,
+                });
+            }
+        }
+    }
+
+    History::new(history)
+}
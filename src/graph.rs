@@ -0,0 +1,243 @@
+use crate::ser_checker::SerChecker;
+use crate::transaction::{Key, Transaction, Value};
+use crate::collections::{BTreeSet, HashMap, HashSet};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// Both transactions belong to the same client, the source comes first.
+    Session,
+    /// The target reads a value written by the source.
+    WriteRead,
+    /// The target overwrites a value written by the source.
+    WriteWrite,
+    /// The target overwrites the value read by the source.
+    ReadWrite,
+}
+
+impl EdgeKind {
+    fn label(&self) -> &'static str {
+        match self {
+            EdgeKind::Session => "so",
+            EdgeKind::WriteRead => "wr",
+            EdgeKind::WriteWrite => "ww",
+            EdgeKind::ReadWrite => "rw",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Edge<K: Key> {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub kind: EdgeKind,
+    /// The key the dependency comes from, `None` for session edges.
+    pub key: Option<K>,
+}
+
+// the successors of every node, each with whether the edge to it is an
+// anti-dependency
+type Successors = HashMap<(usize, usize), Vec<((usize, usize), bool)>>;
+
+/// Dependencies between the transactions of a history. Nodes are
+/// `(client, depth)` pairs and `init` names the synthetic init transaction.
+///
+/// Only dependencies which can be inferred unambiguously are included: a read
+/// is attributed to its writer only if exactly one transaction wrote the
+/// value, and a write is ordered after another one only if it read the value
+/// it overwrote or both belong to the same client.
+#[derive(Clone, Debug)]
+pub struct DepGraph<K: Key> {
+    pub nodes: Vec<(usize, usize)>,
+    pub edges: Vec<Edge<K>>,
+    pub init: Option<(usize, usize)>,
+}
+
+impl<K: Key> DepGraph<K> {
+    /// Builds the graph of `transactions`, in which the only transaction of
+    /// client `init` writes the initial value of every key.
+    pub fn new<V: Value>(transactions: &[Vec<Transaction<K, V>>], init: Option<usize>) -> Self {
+        let mut checker = SerChecker::new(transactions.to_vec());
+        if let Some(init) = init {
+            checker.commit(init);
+        }
+
+        checker.graph()
+    }
+
+    /// Finds a cycle and returns its edges in order.
+    pub fn cycle(&self) -> Option<Vec<Edge<K>>> {
+        let mut visited = HashSet::new();
+        for node in self.nodes.iter() {
+            let mut path = Vec::new();
+            let mut on_path = HashSet::new();
+            if let Some(cycle) = self.find_cycle(*node, &mut visited, &mut on_path, &mut path) {
+                return Some(cycle);
+            }
+        }
+
+        None
+    }
+
+    /// Lists the cycles with at least one anti-dependency (`ReadWrite`)
+    /// edge, the G2 anomalies of Adya. Each cycle is listed once as the
+    /// nodes it passes through, starting from its smallest node, and the
+    /// cycles are ordered. Cycles through the same nodes along different
+    /// edges count as one.
+    pub fn g2_cycles(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut successors: Successors = HashMap::new();
+        for edge in self.edges.iter() {
+            successors
+                .entry(edge.from)
+                .or_default()
+                .push((edge.to, edge.kind == EdgeKind::ReadWrite));
+        }
+
+        // every cycle is found from its smallest node, through larger ones only
+        let mut cycles = BTreeSet::new();
+        for start in self.nodes.iter() {
+            let mut path = alloc::vec![*start];
+            Self::extend_cycles(&successors, *start, false, &mut path, &mut cycles);
+        }
+
+        cycles.into_iter().collect()
+    }
+
+    fn extend_cycles(
+        successors: &Successors,
+        start: (usize, usize),
+        anti: bool,
+        path: &mut Vec<(usize, usize)>,
+        cycles: &mut BTreeSet<Vec<(usize, usize)>>,
+    ) {
+        let node = *path.last().unwrap();
+        for (next, rw) in successors.get(&node).into_iter().flatten() {
+            if *next == start {
+                if anti || *rw {
+                    cycles.insert(path.clone());
+                }
+            } else if *next > start && !path.contains(next) {
+                path.push(*next);
+                Self::extend_cycles(successors, start, anti || *rw, path, cycles);
+                path.pop();
+            }
+        }
+    }
+
+    /// Orders the nodes so that every edge points forward, taking the
+    /// smallest node whose predecessors are all ordered first, or returns
+    /// `None` if there is a cycle.
+    pub fn topological_order(&self) -> Option<Vec<(usize, usize)>> {
+        let mut predecessors: HashMap<(usize, usize), usize> = self.nodes.iter().map(|node| (*node, 0)).collect();
+        let mut successors: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+        for edge in self.edges.iter() {
+            successors.entry(edge.from).or_default().push(edge.to);
+            *predecessors.entry(edge.to).or_insert(0) += 1;
+        }
+
+        let mut ready: BTreeSet<(usize, usize)> =
+            predecessors.iter().filter(|(_, n)| **n == 0).map(|(node, _)| *node).collect();
+        let mut order = Vec::new();
+        while let Some(node) = ready.pop_first() {
+            order.push(node);
+            for next in successors.get(&node).into_iter().flatten() {
+                let n = predecessors.get_mut(next).unwrap();
+                *n -= 1;
+                if *n == 0 {
+                    ready.insert(*next);
+                }
+            }
+        }
+
+        if order.len() < predecessors.len() {
+            None
+        } else {
+            Some(order)
+        }
+    }
+
+    fn find_cycle(
+        &self,
+        node: (usize, usize),
+        visited: &mut HashSet<(usize, usize)>,
+        on_path: &mut HashSet<(usize, usize)>,
+        path: &mut Vec<Edge<K>>,
+    ) -> Option<Vec<Edge<K>>> {
+        if !visited.insert(node) {
+            return None;
+        }
+        on_path.insert(node);
+
+        for edge in self.edges.iter().filter(|e| e.from == node) {
+            path.push(edge.clone());
+            if on_path.contains(&edge.to) {
+                let start = path.iter().position(|e| e.from == edge.to).unwrap();
+                return Some(path[start..].to_vec());
+            }
+            if let Some(cycle) = self.find_cycle(edge.to, visited, on_path, path) {
+                return Some(cycle);
+            }
+            path.pop();
+        }
+
+        on_path.remove(&node);
+        None
+    }
+
+    fn node_name(&self, node: (usize, usize)) -> String {
+        if Some(node) == self.init {
+            "init".to_string()
+        } else {
+            format!("t_{}_{}", node.0, node.1)
+        }
+    }
+
+    /// Renders the graph in the Graphviz DOT format, with the edges of
+    /// `highlight` drawn in red.
+    pub fn to_dot_highlighting(&self, highlight: &[Edge<K>]) -> String {
+        let mut dot = String::from("digraph dependencies {\n");
+
+        for node in self.nodes.iter() {
+            let label = if Some(*node) == self.init {
+                "init".to_string()
+            } else {
+                format!("({}, {})", node.0, node.1)
+            };
+            writeln!(dot, "    {} [label=\"{}\"];", self.node_name(*node), label).unwrap();
+        }
+
+        for edge in self.edges.iter() {
+            let label = match &edge.key {
+                Some(key) => format!("{} {:?}", edge.kind.label(), key).replace('"', "\\\""),
+                None => edge.kind.label().to_string(),
+            };
+            let mut attrs = format!("label=\"{}\"", label);
+            if edge.kind == EdgeKind::Session {
+                attrs.push_str(", style=dashed");
+            }
+            if highlight.contains(edge) {
+                attrs.push_str(", color=red");
+            }
+            writeln!(
+                dot,
+                "    {} -> {} [{}];",
+                self.node_name(edge.from),
+                self.node_name(edge.to),
+                attrs
+            )
+            .unwrap();
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph in the Graphviz DOT format, with a cycle drawn in
+    /// red if there is one.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_highlighting(&self.cycle().unwrap_or_default())
+    }
+}
@@ -1,2 +1,19 @@
-mod ser_checker;
-mod transaction;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[cfg(all(test, not(feature = "std")))]
+extern crate std;
+
+pub mod anomaly;
+mod collections;
+#[cfg(feature = "elle")]
+pub mod elle;
+pub mod error;
+pub mod gen;
+pub mod graph;
+#[cfg(feature = "sat")]
+pub mod sat;
+pub mod ser_checker;
+pub mod transaction;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,201 @@
+//! Checks a history read from a file, or from stdin if no path is given, and
+//! prints the verdict of every isolation level. The exit code is 0 if every
+//! checked level holds, 1 if any of them is violated and 2 if the history
+//! cannot be read.
+//!
+//! Histories are either the JSON serialization of `History<String, u64>`
+//! (with the `serde` feature) or a text format with one committed
+//! transaction per line, prefixed by the index of its client:
+//!
+//! ```text
+//! # write skew
+//! 0: get(x)=0 get(y)=0 set(x)=1
+//! 1: get(x)=0 get(y)=0 set(y)=1
+//! ```
+//!
+//! The operations are `get(k)=v`, `set(k)=v`, `append(k)=v`,
+//! `read(k)=[v,v]`, `delete(k)`, `absent(k)`, `cas(k)=e->n` and the failed
+//! `cas(k)!=e->n`. A line ending with `abort` is an aborted transaction.
+
+use ergosum::transaction::{History, Transaction, TransactionBuilder};
+use std::io::Read;
+use std::process::exit;
+
+const USAGE: &str = "usage: ergosum [--level LEVEL]... [PATH]
+
+levels: ser, si, gsi, prefix, causal, cs, mav, rc, ru";
+
+const LEVELS: [&str; 9] = ["ser", "si", "gsi", "prefix", "causal", "cs", "mav", "rc", "ru"];
+
+fn check(history: &History<String, u64>, level: &str) -> bool {
+    match level {
+        "ser" => history.ser_check(),
+        "si" => history.si_check(),
+        "gsi" => history.gsi_check(),
+        "prefix" => history.prefix_check(),
+        "causal" => history.causal_check(),
+        "cs" => history.cursor_stability_check(),
+        "mav" => history.mav_check(),
+        "rc" => history.read_committed_check(),
+        "ru" => history.read_uncommitted_check(),
+        _ => unreachable!(),
+    }
+}
+
+// splits `name(key)rest` into the key and the rest
+fn operand<'a>(op: &'a str, name: &str) -> Option<(String, &'a str)> {
+    let rest = op.strip_prefix(name)?.strip_prefix('(')?;
+    let end = rest.find(')')?;
+
+    Some((rest[..end].to_string(), &rest[end + 1..]))
+}
+
+fn value(s: &str) -> Result<u64, String> {
+    s.parse().map_err(|_| format!("invalid value {:?}", s))
+}
+
+fn parse_op(op: &str, transaction: &mut TransactionBuilder<String, u64>) -> Result<(), String> {
+    let invalid = || format!("invalid operation {:?}", op);
+
+    if let Some((key, rest)) = operand(op, "get") {
+        transaction.get(key, value(rest.strip_prefix('=').ok_or_else(invalid)?)?);
+    } else if let Some((key, rest)) = operand(op, "set") {
+        transaction.set(key, value(rest.strip_prefix('=').ok_or_else(invalid)?)?);
+    } else if let Some((key, rest)) = operand(op, "append") {
+        transaction.append(key, value(rest.strip_prefix('=').ok_or_else(invalid)?)?);
+    } else if let Some((key, rest)) = operand(op, "read") {
+        let list = rest
+            .strip_prefix("=[")
+            .and_then(|rest| rest.strip_suffix(']'))
+            .ok_or_else(invalid)?;
+        let vals = if list.is_empty() {
+            Vec::new()
+        } else {
+            list.split(',').map(value).collect::<Result<_, _>>()?
+        };
+        transaction.read(key, vals);
+    } else if let Some((key, rest)) = operand(op, "delete") {
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+        transaction.delete(key);
+    } else if let Some((key, rest)) = operand(op, "absent") {
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+        transaction.get_absent(key);
+    } else if let Some((key, rest)) = operand(op, "cas") {
+        let (succeeded, rest) = match rest.strip_prefix("!=") {
+            Some(rest) => (false, rest),
+            None => (true, rest.strip_prefix('=').ok_or_else(invalid)?),
+        };
+        let (expected, new) = rest.split_once("->").ok_or_else(invalid)?;
+        transaction.cas(key, value(expected)?, value(new)?, succeeded);
+    } else {
+        return Err(invalid());
+    }
+
+    Ok(())
+}
+
+fn parse_dsl(input: &str) -> Result<History<String, u64>, String> {
+    let mut clients: Vec<Vec<Transaction<String, u64>>> = Vec::new();
+
+    for (number, line) in input.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let at = |e: String| format!("line {}: {}", number + 1, e);
+        let (client, ops) = line.split_once(':').ok_or_else(|| at("missing client".to_string()))?;
+        let client: usize = client
+            .trim()
+            .parse()
+            .map_err(|_| at(format!("invalid client {:?}", client.trim())))?;
+
+        let mut transaction = Transaction::builder();
+        for op in ops.split_whitespace() {
+            if op == "abort" {
+                transaction.abort();
+            } else {
+                parse_op(op, &mut transaction).map_err(at)?;
+            }
+        }
+
+        if clients.len() <= client {
+            clients.resize(client + 1, Vec::new());
+        }
+        clients[client].push(transaction.build());
+    }
+
+    Ok(History::new(clients))
+}
+
+#[cfg(feature = "serde")]
+fn parse_json(input: &str) -> Result<History<String, u64>, String> {
+    History::from_json_str(input).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "serde"))]
+fn parse_json(_: &str) -> Result<History<String, u64>, String> {
+    Err("reading JSON histories needs the serde feature".to_string())
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("ergosum: {}", message);
+    exit(2)
+}
+
+fn main() {
+    let mut levels = Vec::new();
+    let mut path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--level" => match args.next() {
+                Some(level) if LEVELS.contains(&level.as_str()) => levels.push(level),
+                Some(level) => fail(&format!("unknown level {:?}\n{}", level, USAGE)),
+                None => fail(USAGE),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => fail(USAGE),
+        }
+    }
+    if levels.is_empty() {
+        levels = LEVELS.iter().map(|level| level.to_string()).collect();
+    }
+
+    let mut input = String::new();
+    let read = match path.as_deref() {
+        None | Some("-") => std::io::stdin().read_to_string(&mut input).map(|_| ()),
+        Some(path) => std::fs::read_to_string(path).map(|content| input = content),
+    };
+    if let Err(e) = read {
+        fail(&e.to_string());
+    }
+
+    let history = if input.trim_start().starts_with('{') {
+        parse_json(&input)
+    } else {
+        parse_dsl(&input)
+    };
+    let history = history.unwrap_or_else(|e| fail(&e));
+    if !history.is_recoverable() {
+        eprintln!("ergosum: warning: the history is not recoverable");
+    }
+
+    let mut passed = true;
+    for level in levels.iter() {
+        let holds = check(&history, level);
+        println!("{}: {}", level, if holds { "pass" } else { "fail" });
+        passed &= holds;
+    }
+
+    exit(if passed { 0 } else { 1 })
+}
@@ -0,0 +1,304 @@
+//! Checks serializability by encoding the order of the transactions as a
+//! SAT instance instead of searching it.
+//!
+//! Every pair of transactions gets a variable telling whether the first one
+//! comes before the second, and clauses forbidding three transactions in a
+//! cycle keep the order transitive. Sessions and other precedences are unit
+//! clauses. Every read of another transaction picks one of the committed
+//! transactions writing the value last, or the initial value, which has to
+//! come before the reader while every other writer of the key comes before
+//! the source or after the reader. The instance takes `O(n^3)` clauses for
+//! `n` transactions, however the reads go, so it pays off where the search
+//! has to backtrack a lot rather than for long histories without conflicts.
+//!
+//! Only gets, sets and successful compare-and-sets of committed transactions
+//! without versions are encoded, see `supports`.
+
+use crate::collections::HashMap;
+use crate::transaction::{Key, Op, Precedence, Transaction, Value};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A literal as in DIMACS: variable `v` counts from 1, and `-v` is its
+/// negation.
+pub type Lit = i32;
+
+/// Decides a CNF instance over the variables `1..=vars`, so another solver
+/// can take the place of the bundled `Dpll`.
+pub trait Solver {
+    /// Returns the value of every variable, indexed from 0 for variable 1,
+    /// if the clauses can all be satisfied.
+    fn solve(&mut self, vars: usize, clauses: &[Vec<Lit>]) -> Option<Vec<bool>>;
+}
+
+/// A DPLL solver with unit propagation over two watched literals per
+/// clause and chronological backtracking.
+#[derive(Clone, Debug, Default)]
+pub struct Dpll {
+    /// How many variables the solves so far have decided on.
+    pub decisions: u64,
+}
+
+// the index of a literal among the watch lists
+fn slot(lit: Lit) -> usize {
+    2 * (lit.unsigned_abs() as usize - 1) + (lit < 0) as usize
+}
+
+impl Dpll {
+    fn value(assignment: &[Option<bool>], lit: Lit) -> Option<bool> {
+        assignment[lit.unsigned_abs() as usize - 1].map(|val| val == (lit > 0))
+    }
+
+    // assigns the literals of the trail from `head` on, and tells whether
+    // no clause became false
+    fn propagate(
+        clauses: &mut [Vec<Lit>],
+        watches: &mut [Vec<usize>],
+        assignment: &mut [Option<bool>],
+        trail: &mut Vec<Lit>,
+        head: &mut usize,
+    ) -> bool {
+        while *head < trail.len() {
+            let falsified = -trail[*head];
+            *head += 1;
+
+            let watching = core::mem::take(&mut watches[slot(falsified)]);
+            let mut kept = Vec::with_capacity(watching.len());
+            let mut conflict = false;
+            for (i, &c) in watching.iter().enumerate() {
+                if conflict {
+                    kept.extend_from_slice(&watching[i..]);
+                    break;
+                }
+
+                let clause = &mut clauses[c];
+                if clause[0] == falsified {
+                    clause.swap(0, 1);
+                }
+                if Self::value(assignment, clause[0]) == Some(true) {
+                    kept.push(c);
+                    continue;
+                }
+                match (2..clause.len()).find(|&k| Self::value(assignment, clause[k]) != Some(false)) {
+                    Some(k) => {
+                        clause.swap(1, k);
+                        watches[slot(clause[1])].push(c);
+                    }
+                    None => {
+                        kept.push(c);
+                        match Self::value(assignment, clause[0]) {
+                            Some(false) => conflict = true,
+                            _ => {
+                                assignment[clause[0].unsigned_abs() as usize - 1] = Some(clause[0] > 0);
+                                trail.push(clause[0]);
+                            }
+                        }
+                    }
+                }
+            }
+            watches[slot(falsified)] = kept;
+
+            if conflict {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl Solver for Dpll {
+    fn solve(&mut self, vars: usize, clauses: &[Vec<Lit>]) -> Option<Vec<bool>> {
+        let mut assignment: Vec<Option<bool>> = vec![None; vars];
+        let mut trail = Vec::new();
+        let mut head = 0;
+
+        // a clause of a single literal has nothing to watch but its literal
+        let mut watched = Vec::new();
+        let mut watches = vec![Vec::new(); 2 * vars];
+        for clause in clauses.iter() {
+            let mut clause = clause.clone();
+            clause.sort_unstable();
+            clause.dedup();
+            if clause.iter().any(|lit| clause.contains(&-lit)) {
+                continue;
+            }
+            match clause[..] {
+                [] => return None,
+                [lit] => match Self::value(&assignment, lit) {
+                    Some(false) => return None,
+                    Some(true) => {}
+                    None => {
+                        assignment[lit.unsigned_abs() as usize - 1] = Some(lit > 0);
+                        trail.push(lit);
+                    }
+                },
+                _ => {
+                    watches[slot(clause[0])].push(watched.len());
+                    watches[slot(clause[1])].push(watched.len());
+                    watched.push(clause);
+                }
+            }
+        }
+
+        // the trail length before each decision, the decided literal and
+        // whether it is the second try
+        let mut decisions: Vec<(usize, Lit, bool)> = Vec::new();
+        loop {
+            if Self::propagate(&mut watched, &mut watches, &mut assignment, &mut trail, &mut head) {
+                match assignment.iter().position(Option::is_none) {
+                    Some(var) => {
+                        self.decisions += 1;
+                        let lit = -(var as Lit + 1);
+                        decisions.push((trail.len(), lit, false));
+                        assignment[var] = Some(false);
+                        trail.push(lit);
+                    }
+                    None => return Some(assignment.into_iter().map(|val| val == Some(true)).collect()),
+                }
+                continue;
+            }
+
+            loop {
+                let (start, lit, flipped) = decisions.pop()?;
+                for undone in trail.drain(start..) {
+                    assignment[undone.unsigned_abs() as usize - 1] = None;
+                }
+                head = start;
+                if !flipped {
+                    decisions.push((start, -lit, true));
+                    assignment[lit.unsigned_abs() as usize - 1] = Some(-lit > 0);
+                    trail.push(-lit);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Tells whether the committed transactions only get, set and successfully
+/// compare-and-set keys without versions, which is what `serial_order`
+/// encodes.
+pub fn supports<K: Key, V: Value>(transactions: &[Vec<Transaction<K, V>>]) -> bool {
+    transactions.iter().flatten().filter(|t| !t.aborted).flat_map(|t| t.ops.iter()).all(|op| match op {
+        Op::Set(set) => set.version.is_none(),
+        Op::Get(get) => get.version.is_none(),
+        Op::Cas(cas) => cas.succeeded,
+        Op::Append(_)
+        | Op::Read(_)
+        | Op::Delete(_)
+        | Op::GetAbsent(_)
+        | Op::RangeGet(_)
+        | Op::Incr(_)
+        | Op::RangeDelete(_) => false,
+    })
+}
+
+/// Solves the order of `transactions` with `solver`, which has to keep
+/// their sessions and `precedences`, and returns it as `(client, depth)`
+/// pairs if there is one. Keys start out as `initial` gives, and the
+/// transactions have to be `supports`ed.
+pub fn serial_order<K: Key, V: Value, S: Solver>(
+    transactions: &[Vec<Transaction<K, V>>],
+    precedences: &[Precedence],
+    initial: impl Fn(&K) -> V,
+    solver: &mut S,
+) -> Option<Vec<(usize, usize)>> {
+    let nodes: Vec<(usize, usize)> =
+        transactions.iter().enumerate().flat_map(|(c, client)| (0..client.len()).map(move |d| (c, d))).collect();
+    let node: HashMap<(usize, usize), usize> = nodes.iter().enumerate().map(|(i, t)| (*t, i)).collect();
+    let n = nodes.len();
+
+    // variable `before(i, j)` for `i < j` tells that `i` comes first
+    let pair = |i: usize, j: usize| (i * (2 * n - i - 1) / 2 + (j - i - 1) + 1) as Lit;
+    let before = |i: usize, j: usize| if i < j { pair(i, j) } else { -pair(j, i) };
+    let mut vars = n * n.saturating_sub(1) / 2;
+
+    let mut clauses = Vec::new();
+    for i in 0..n {
+        for j in i + 1..n {
+            for k in j + 1..n {
+                clauses.push(vec![-before(i, j), -before(j, k), -before(k, i)]);
+                clauses.push(vec![-before(i, k), -before(k, j), -before(j, i)]);
+            }
+        }
+    }
+    for (c, client) in transactions.iter().enumerate() {
+        for d in 1..client.len() {
+            clauses.push(vec![before(node[&(c, d - 1)], node[&(c, d)])]);
+        }
+    }
+    for (earlier, later) in precedences.iter() {
+        clauses.push(vec![before(node[earlier], node[later])]);
+    }
+
+    // the last write of every key, and the reads of other transactions,
+    // which all have to agree for a key
+    let mut writers: HashMap<K, Vec<(usize, V)>> = HashMap::new();
+    let mut reads = Vec::new();
+    for (i, (c, d)) in nodes.iter().enumerate() {
+        let t = &transactions[*c][*d];
+        if t.aborted {
+            continue;
+        }
+
+        let mut written: HashMap<K, V> = HashMap::new();
+        let mut read: HashMap<K, V> = HashMap::new();
+        for op in t.expand_cas().ops.into_iter() {
+            match op {
+                Op::Get(get) => {
+                    let observed = written.get(&get.key).or_else(|| read.get(&get.key));
+                    match observed {
+                        Some(val) if *val != get.val => return None,
+                        Some(_) => {}
+                        None => {
+                            read.insert(get.key, get.val);
+                        }
+                    }
+                }
+                Op::Set(set) => {
+                    written.insert(set.key, set.val);
+                }
+                _ => {}
+            }
+        }
+        for (key, val) in written.into_iter() {
+            writers.entry(key).or_default().push((i, val));
+        }
+        reads.extend(read.into_iter().map(|(key, val)| (i, key, val)));
+    }
+
+    for (reader, key, val) in reads.into_iter() {
+        let others: Vec<&(usize, V)> = writers.get(&key).into_iter().flatten().filter(|(w, _)| *w != reader).collect();
+        let mut sources: Vec<Option<usize>> = others.iter().filter(|(_, v)| *v == val).map(|(w, _)| Some(*w)).collect();
+        if val == initial(&key) {
+            sources.push(None);
+        }
+
+        let mut choices = Vec::new();
+        for source in sources.into_iter() {
+            vars += 1;
+            let chosen = vars as Lit;
+            choices.push(chosen);
+            if let Some(source) = source {
+                clauses.push(vec![-chosen, before(source, reader)]);
+            }
+            for (other, _) in others.iter().filter(|(w, _)| Some(*w) != source) {
+                match source {
+                    Some(source) => clauses.push(vec![-chosen, before(*other, source), before(reader, *other)]),
+                    None => clauses.push(vec![-chosen, before(reader, *other)]),
+                }
+            }
+        }
+        clauses.push(choices);
+    }
+
+    // the order is total, so a transaction comes after as many others as
+    // its position
+    let model = solver.solve(vars, &clauses)?;
+    let holds = |lit: Lit| model[lit.unsigned_abs() as usize - 1] == (lit > 0);
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by_key(|&i| (0..n).filter(|&j| j != i && holds(before(j, i))).count());
+
+    Some(order.into_iter().map(|i| nodes[i]).collect())
+}
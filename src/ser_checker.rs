@@ -1,47 +1,673 @@
-use crate::transaction::{Op, Transaction, Key, Value};
-use std::collections::{HashMap, HashSet};
+use crate::gen::Rng;
+use crate::graph::{DepGraph, Edge, EdgeKind};
+use crate::transaction::{Get, Op, Transaction, Key, Value, VersionId};
+use crate::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "parallel")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
 
+#[derive(Clone)]
 pub struct SerChecker<K: Key, V: Value> {
     pub transactions: Vec<Vec<Transaction<K, V>>>,
 
     pub searched: Vec<usize>,
-    pub searched_cache: HashMap<Vec<usize>, bool>,
+    /// Verdicts of the states searched by this checker. `CheckCache`
+    /// remembers verdicts of whole histories instead.
+    pub searched_cache: Cache,
 
     pub kv_rev: HashMap<(K, V), HashSet<(usize, usize)>>,
+    // the transactions whose last write to a key creates an explicit version
+    pub versions_rev: HashMap<(K, VersionId), HashSet<(usize, usize)>>,
+    // the transactions whose last write to a key deletes it
+    pub deletes: HashMap<K, HashSet<(usize, usize)>>,
+    // the transactions whose last write to a key increments it, which may
+    // leave any value behind
+    pub increments: HashMap<K, HashSet<(usize, usize)>>,
+    // reads of a transaction's own writes, which other transactions cannot affect
+    own_reads: Vec<Vec<Vec<bool>>>,
+    // what is known about every operation before searching, so the search
+    // neither hashes nor clones keys and values
+    op_info: Vec<Vec<Vec<OpInfo>>>,
+    // the keys every transaction writes, each once
+    write_keys: Vec<Vec<Vec<usize>>>,
+    // the same keys as sets, so telling whether a transaction writes a key
+    // takes one lookup
+    write_sets: Vec<Vec<HashSet<usize>>>,
+    // the reads of every key which observe other transactions, per client
+    outside_reads: Vec<Vec<Vec<OutsideRead>>>,
+    // the transactions of other clients which have to commit before a
+    // transaction, see `precede`
+    after: HashMap<(usize, usize), Vec<(usize, usize)>>,
+
+    // several transactions may write the same value to a key, so the value a
+    // key currently holds depends on the order and is part of the search state.
+    // A key which is never written or deleted holds `None`. `versions` holds
+    // the explicit version of every register, if its last write has one.
+    // Keys and values are numbered once, so the state holds their ids and the
+    // search never clones a key or a value; `key` and `value` map them back.
+    pub keys: HashMap<K, usize>,
+    pub current: Vec<Option<u32>>,
+    pub versions: Vec<Option<VersionId>>,
+    pub lists: Vec<Vec<u32>>,
+    key_names: Vec<K>,
+    // `values[0]` is `V::initial_value()`
+    values: Vec<V>,
+    value_ids: HashMap<V, u32>,
+
+    pub order: Vec<(usize, usize)>,
+    /// Resolves writes like a last-write-wins register: the version of a
+    /// write is its timestamp, and a write older than the version it would
+    /// overwrite is lost. Writes and deletes without a version always take
+    /// effect.
+    pub last_write_wins: bool,
+    /// The order in which the search tries the clients. It changes how soon
+    /// an order is found, but not whether there is one.
+    pub search_order: SearchOrder,
+    // how many transactions at the start of `order` were committed by `commit`
+    committed: usize,
+    pub deepest: Option<Anomaly<K, V>>,
+
+    stats: SerCheckerStats,
+
+    // the search gives up once this has passed, or once `stats` counts
+    // this many nodes explored
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    gave_up: bool,
+
+    // scans every pending transaction for readers instead of using `outside_reads`
+    #[cfg(test)]
+    pub(crate) naive: bool,
+    // searches below states in which some read can no longer be satisfied
+    #[cfg(test)]
+    pub(crate) unpruned: bool,
+}
+
+/// Which of the transactions that can be committed next the search tries
+/// first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SearchOrder {
+    /// By the index of their clients.
+    #[default]
+    IndexOrder,
+    /// Those with the most reads of other transactions first, since they
+    /// observe the current state now but may not once more is committed.
+    /// Ties go by index.
+    MostConstrainedFirst,
+    /// Shuffled anew in every search state, by a generator seeded with the
+    /// given seed at the start of every check.
+    Random(u64),
+}
+
+/// Counters of the work done by the searches of a checker, accumulated over
+/// every check it ran.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SerCheckerStats {
+    /// Search states entered, i.e. transactions tentatively committed.
+    pub nodes_explored: u64,
+    /// States whose verdict was found in the cache.
+    pub cache_hits: u64,
+    /// States which had to be searched.
+    pub cache_misses: u64,
+    /// The longest prefix searched from, relative to where the search started.
+    pub max_depth: usize,
+}
+
+#[derive(Clone)]
+struct OpInfo {
+    // the index of the key in `keys`
+    key: usize,
+    // the last earlier set or delete of the key in the same transaction
+    own_write: Option<usize>,
+    // the transactions producing what a get or a read of an absent key observes
+    sources: Vec<(usize, usize)>,
+    // the value a set, a get or an append carries, or a failed
+    // compare-and-set expects
+    val: u32,
+    // the list a read observes
+    vals: Vec<u32>,
+    // the keys a range read covers and the keys and values it observes, both
+    // ordered by key
+    range: Vec<usize>,
+    observed: Vec<(usize, u32)>,
+}
+
+// a read which a write of the same key could hide from its sources
+#[derive(Clone)]
+struct OutsideRead {
+    depth: usize,
+    op: usize,
+}
+
+// why the next transaction of a client cannot be committed, cheap enough to
+// find for every candidate of the search and turned into a `Blocker` only
+// when it is reported
+#[derive(Clone, Copy)]
+enum Block {
+    // operation `0` of the transaction does not observe the current state
+    Read(usize),
+    // operation `0` of the transaction contradicts its own earlier write `1`
+    Own(usize, usize),
+    // the transaction hides what operation `2` of transaction `(0, 1)` observes
+    Hide(usize, usize, usize),
+    // transaction `(0, 1)` has to commit first
+    After(usize, usize),
+}
+
+// committed transactions of every client, the value and the version of every
+// register and the content of every list, with values as their ids
+pub type State = (Vec<usize>, Vec<Option<u32>>, Vec<Option<VersionId>>, Vec<Vec<u32>>);
+
+/// Memoized verdicts of search states. With a capacity the least recently
+/// used state is evicted first, which only costs searching it again.
+#[derive(Clone)]
+pub struct Cache {
+    pub capacity: Option<usize>,
+
+    entries: HashMap<State, (bool, u64)>,
+    recency: BTreeMap<u64, State>,
+    tick: u64,
+
+    // failed states shared between checkers searching in parallel
+    #[cfg(feature = "parallel")]
+    shared: Option<Arc<Mutex<HashSet<State>>>>,
+}
+
+impl Cache {
+    pub fn new(capacity: Option<usize>) -> Self {
+        Cache {
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            #[cfg(feature = "parallel")]
+            shared: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&mut self, state: &State) -> Option<bool> {
+        self.tick += 1;
+        let tick = self.tick;
+
+        let (value, used) = match self.entries.get_mut(state) {
+            Some(entry) => entry,
+            None => {
+                #[cfg(feature = "parallel")]
+                if let Some(shared) = &self.shared {
+                    if shared.lock().unwrap().contains(state) {
+                        return Some(false);
+                    }
+                }
+                return None;
+            }
+        };
+        let state = self.recency.remove(used).unwrap();
+        *used = tick;
+        self.recency.insert(tick, state);
+
+        Some(*value)
+    }
+
+    /// Keeps only the states for which `keep` holds, given their verdicts.
+    pub fn retain(&mut self, mut keep: impl FnMut(&State, bool) -> bool) {
+        let recency = &mut self.recency;
+        self.entries.retain(|state, (value, used)| {
+            let kept = keep(state, *value);
+            if !kept {
+                recency.remove(used);
+            }
+            kept
+        });
+    }
+
+    pub fn insert(&mut self, state: State, value: bool) {
+        #[cfg(feature = "parallel")]
+        if let (Some(shared), false) = (&self.shared, value) {
+            shared.lock().unwrap().insert(state.clone());
+        }
+        if self.capacity == Some(0) {
+            return;
+        }
+
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.insert(state.clone(), (value, self.tick)) {
+            self.recency.remove(&used);
+        }
+        self.recency.insert(self.tick, state);
+
+        if let Some(capacity) = self.capacity {
+            while self.entries.len() > capacity {
+                let (_, oldest) = self.recency.pop_first().unwrap();
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+// the changes made by a committed transaction, in the order they happened
+enum Change {
+    Set(usize, Option<u32>, Option<VersionId>),
+    Append(usize),
+}
+
+type Undo = Vec<Change>;
+
+struct Frame {
+    next: usize,
+    // the clients in the order they are tried, by index if empty
+    clients: Vec<usize>,
+    entered: Option<(usize, Undo)>,
+}
+
+/// The reason why a transaction cannot be appended to a serial order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Blocker<K: Key, V: Value> {
+    /// The transaction reads `key = val`, but `key` holds another value. Any of
+    /// `writers` could produce `val`.
+    ReadFrom { key: K, val: V, writers: Vec<(usize, usize)> },
+    /// The transaction writes `key`, which would hide `val` from the pending `reader`.
+    Overwrite { key: K, val: V, reader: (usize, usize) },
+    /// The transaction reads `key = val` after writing `written` to it itself,
+    /// so it is inconsistent whatever the order.
+    OwnWrite { key: K, val: V, written: V },
+    /// The transaction reads `key` as absent, but `key` holds a value. Any of
+    /// `deleters` could delete it.
+    ReadAbsent { key: K, deleters: Vec<(usize, usize)> },
+    /// The transaction writes `key`, after which the pending `reader` could no
+    /// longer observe it absent.
+    Undelete { key: K, reader: (usize, usize) },
+    /// The transaction reads a value of `key` after deleting it itself, or
+    /// reads it as absent after setting it.
+    OwnDelete { key: K },
+    /// The compare-and-set of the transaction failed, but `key` holds the
+    /// expected `val`.
+    FailedCas { key: K, val: V },
+    /// The transaction reads the list `vals` from `key`, but `key` holds another list.
+    ReadList { key: K, vals: Vec<V> },
+    /// The transaction appends to `key`, after which the pending `reader` could
+    /// no longer observe the list it read.
+    Append { key: K, reader: (usize, usize) },
+    /// The transaction reads `observed` as the present keys of `start..end`,
+    /// but the range holds other keys or values.
+    ReadRange { start: K, end: K, observed: Vec<(K, V)> },
+    /// The transaction increments `key`, but it holds `val`, which cannot be
+    /// incremented.
+    Increment { key: K, val: V },
+    /// The transaction has to commit after `earlier`, which has not
+    /// committed yet.
+    Precedence { earlier: (usize, usize) },
+}
+
+/// A failed serializability check.
+///
+/// `order` is the longest serial prefix the search managed to build, and
+/// `blocked` lists the next transaction of every unfinished client together
+/// with the dependency that kept it out of the order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Anomaly<K: Key, V: Value> {
+    pub order: Vec<(usize, usize)>,
+    pub blocked: Vec<((usize, usize), Blocker<K, V>)>,
+}
+
+impl<K: Key, V: Value> Default for Anomaly<K, V> {
+    fn default() -> Self {
+        Anomaly {
+            order: Vec::new(),
+            blocked: Vec::new(),
+        }
+    }
 }
 
 impl<K: Key, V: Value> SerChecker<K, V> {
     pub fn new(transactions: Vec<Vec<Transaction<K, V>>>) -> Self {
-        let searched = vec![0; transactions.len()];
+        Self::with_cache_capacity(transactions, None)
+    }
+
+    /// Creates a checker which remembers at most `capacity` search states,
+    /// or all of them if `capacity` is `None`.
+    pub fn with_cache_capacity(transactions: Vec<Vec<Transaction<K, V>>>, capacity: Option<usize>) -> Self {
+        Self::from_borrowed_with_capacity(&transactions, None, capacity)
+    }
+
+    /// Creates a checker of `transactions` without taking them, followed by
+    /// a client running only `init`, if any, which is committed before
+    /// anything else, e.g. the transaction writing the initial value of
+    /// every key. The checker keeps its own copies of the transactions with
+    /// compare-and-sets expanded, so a history is checked without cloning it
+    /// first. Range deletes are expanded as well, into deletes of the keys
+    /// the transactions and `init` touch, so the search never sees them.
+    pub fn from_borrowed(transactions: &[Vec<Transaction<K, V>>], init: Option<Transaction<K, V>>) -> Self {
+        Self::from_borrowed_with_capacity(transactions, init, None)
+    }
+
+    fn from_borrowed_with_capacity(
+        transactions: &[Vec<Transaction<K, V>>],
+        init: Option<Transaction<K, V>>,
+        capacity: Option<usize>,
+    ) -> Self {
+        let keys: BTreeSet<K> =
+            transactions.iter().flatten().chain(init.iter()).flat_map(|t| t.touched()).cloned().collect();
+        let mut transactions: Vec<Vec<Transaction<K, V>>> = transactions
+            .iter()
+            .map(|client| client.iter().map(|t| Self::normalize(t, &keys)).collect())
+            .collect();
+        let init = init.map(|init| {
+            transactions.push(vec![Self::normalize(&init, &keys)]);
+            transactions.len() - 1
+        });
+
+        let mut checker = Self {
+            searched: vec![0; transactions.len()],
+            transactions,
+            kv_rev: HashMap::new(),
+            versions_rev: HashMap::new(),
+            deletes: HashMap::new(),
+            increments: HashMap::new(),
+            own_reads: Vec::new(),
+            op_info: Vec::new(),
+            write_keys: Vec::new(),
+            write_sets: Vec::new(),
+            outside_reads: Vec::new(),
+            after: HashMap::new(),
+            keys: HashMap::new(),
+            current: Vec::new(),
+            versions: Vec::new(),
+            lists: Vec::new(),
+            key_names: Vec::new(),
+            values: vec![V::initial_value()],
+            value_ids: core::iter::once((V::initial_value(), 0)).collect(),
+            searched_cache: Cache::new(capacity),
+            order: Vec::new(),
+            last_write_wins: false,
+            search_order: SearchOrder::IndexOrder,
+            committed: 0,
+            deepest: None,
+            stats: SerCheckerStats::default(),
+            #[cfg(feature = "std")]
+            deadline: None,
+            node_limit: None,
+            gave_up: false,
+            #[cfg(test)]
+            naive: false,
+            #[cfg(test)]
+            unpruned: false,
+        };
+        for c in 0..checker.transactions.len() {
+            for d in 0..checker.transactions[c].len() {
+                checker.register((c, d));
+            }
+        }
+        checker.index();
+        if let Some(init) = init {
+            checker.commit(init);
+        }
+
+        checker
+    }
+
+    // an aborted transaction stays in its client to keep the indices, but it
+    // neither reads nor writes anything
+    fn normalize(t: &Transaction<K, V>, keys: &BTreeSet<K>) -> Transaction<K, V> {
+        if t.aborted {
+            Transaction {
+                ops: Vec::new(),
+                aborted: true,
+                time: None,
+                interval: None,
+            }
+        } else {
+            t.expand_cas().expand_range_deletes(keys)
+        }
+    }
+
+    // adds the keys transaction `(c, d)` touches and the values it leaves
+    // behind to the lookups
+    fn register(&mut self, (c, d): (usize, usize)) {
+        // only the last write to a key is visible outside of the transaction
+        let mut final_writes = HashMap::new();
+        let mut incremented = HashSet::new();
+        let mut touched = Vec::new();
+        let mut vals = Vec::new();
+        for op in self.transactions[c][d].ops.iter() {
+            match op {
+                Op::Set(set) => {
+                    final_writes.insert(set.key.clone(), Some((set.val.clone(), set.version)));
+                    incremented.remove(&set.key);
+                }
+                Op::Delete(delete) => {
+                    final_writes.insert(delete.key.clone(), None);
+                    incremented.remove(&delete.key);
+                }
+                Op::Incr(incr) => {
+                    final_writes.remove(&incr.key);
+                    incremented.insert(incr.key.clone());
+                }
+                _ => {}
+            }
+            let observed = match op {
+                Op::RangeGet(range) => range.observed.iter().map(|(key, _)| key).collect(),
+                _ => Vec::new(),
+            };
+            touched.extend(core::iter::once(op.key()).chain(observed).cloned());
+            match op {
+                Op::Set(set) => vals.push(&set.val),
+                Op::Get(get) => vals.push(&get.val),
+                Op::Append(append) => vals.push(&append.val),
+                Op::Read(read) => vals.extend(read.vals.iter()),
+                Op::Cas(cas) => vals.extend([&cas.expected, &cas.new]),
+                Op::RangeGet(range) => vals.extend(range.observed.iter().map(|(_, val)| val)),
+                Op::Delete(_) | Op::GetAbsent(_) | Op::Incr(_) | Op::RangeDelete(_) => {}
+            }
+        }
+        let vals: Vec<V> = vals.into_iter().filter(|val| !self.value_ids.contains_key(*val)).cloned().collect();
+
+        for key in touched.into_iter() {
+            if !self.keys.contains_key(&key) {
+                self.keys.insert(key.clone(), self.keys.len());
+                self.key_names.push(key);
+                self.current.push(None);
+                self.versions.push(None);
+                self.lists.push(Vec::new());
+            }
+        }
+        for val in vals.into_iter() {
+            if !self.value_ids.contains_key(&val) {
+                self.value_ids.insert(val.clone(), self.values.len() as u32);
+                self.values.push(val);
+            }
+        }
+
+        for (key, val) in final_writes.into_iter() {
+            match val {
+                Some((val, version)) => {
+                    if let Some(version) = version {
+                        self.versions_rev.entry((key.clone(), version)).or_default().insert((c, d));
+                    }
+                    self.kv_rev.entry((key, val)).or_default().insert((c, d));
+                }
+                None => {
+                    self.deletes.entry(key).or_default().insert((c, d));
+                }
+            }
+        }
+        for key in incremented.into_iter() {
+            self.increments.entry(key).or_default().insert((c, d));
+        }
+    }
+
+    // rebuilds what the search looks up about every operation from the
+    // registered transactions
+    fn index(&mut self) {
+        let transactions = &self.transactions;
+        let keys = &self.keys;
+        let value_ids = &self.value_ids;
+
+        let own_reads: Vec<Vec<Vec<bool>>> = transactions
+            .iter()
+            .map(|client| client.iter().map(|t| t.own_reads()).collect())
+            .collect();
 
-        let mut kv_rev: HashMap<(K, V), HashSet<(usize, usize)>> = HashMap::new();
+        let mut op_info: Vec<Vec<Vec<OpInfo>>> = Vec::new();
+        let mut write_keys: Vec<Vec<Vec<usize>>> = Vec::new();
+        let mut outside_reads: Vec<Vec<Vec<OutsideRead>>> = vec![vec![Vec::new(); transactions.len()]; keys.len()];
         for (c, client) in transactions.iter().enumerate() {
+            let mut client_info = Vec::new();
+            let mut client_write_keys = Vec::new();
+
             for (d, t) in client.iter().enumerate() {
-                for op in t.ops.iter() {
-                    if let Op::Set(set) = op {
-                        match kv_rev.get_mut(&(set.key.clone(), set.val.clone())) {
-                            Some(records) => {
-                                records.insert((c, d));
-                            }
-                            None => {
-                                let mut read_froms = HashSet::new();
-                                read_froms.insert((c, d));
-                                match kv_rev.insert((set.key.clone(), set.val.clone()), read_froms ) {
-                                    Some(_) => {unreachable!()}
-                                    None => {}
-                                }
-                            }
+                let mut info = Vec::new();
+                let mut written = HashMap::new();
+                let mut writes = Vec::new();
+
+                for (o, (op, own)) in t.ops.iter().zip(own_reads[c][d].iter()).enumerate() {
+                    let key = keys[op.key()];
+                    let own_write = written.get(&key).cloned();
+                    if op.is_write() && !writes.contains(&key) {
+                        writes.push(key);
+                    }
+                    if let Op::Set(_) | Op::Delete(_) | Op::Incr(_) = op {
+                        written.insert(key, o);
+                    }
+
+                    // an increment may produce any value of its key, but no version
+                    let writers = match op {
+                        Op::Get(get) => match get.version {
+                            Some(version) => [self.versions_rev.get(&(get.key.clone(), version)), None],
+                            None => [self.kv_rev.get(&(get.key.clone(), get.val.clone())), self.increments.get(&get.key)],
+                        },
+                        Op::GetAbsent(get) => [self.deletes.get(&get.key), None],
+                        Op::Set(_)
+                        | Op::Append(_)
+                        | Op::Read(_)
+                        | Op::Cas(_)
+                        | Op::Delete(_)
+                        | Op::RangeGet(_)
+                        | Op::Incr(_)
+                        | Op::RangeDelete(_) => [None, None],
+                    };
+                    let mut sources: Vec<(usize, usize)> = writers.iter().flatten().copied().flatten().cloned().collect();
+                    sources.sort();
+
+                    // keys which are never written stay absent, so only known keys can be present
+                    let (mut range, mut observed) = (Vec::new(), Vec::new());
+                    if let Op::RangeGet(get) = op {
+                        let mut covered: Vec<(&K, usize)> =
+                            keys.iter().filter(|(key, _)| get.contains(key)).map(|(key, id)| (key, *id)).collect();
+                        covered.sort();
+                        range = covered.into_iter().map(|(_, id)| id).collect();
+
+                        // `RangeGet::new` sorts by key, but the fields are public
+                        let mut sorted: Vec<&(K, V)> = get.observed.iter().collect();
+                        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+                        observed = sorted.into_iter().map(|(key, val)| (keys[key], value_ids[val])).collect();
+                    }
+
+                    let (val, vals) = match op {
+                        Op::Set(set) => (value_ids[&set.val], Vec::new()),
+                        Op::Get(get) => (value_ids[&get.val], Vec::new()),
+                        Op::Append(append) => (value_ids[&append.val], Vec::new()),
+                        Op::Cas(cas) => (value_ids[&cas.expected], Vec::new()),
+                        Op::Read(read) => (0, read.vals.iter().map(|val| value_ids[val]).collect()),
+                        Op::Delete(_) | Op::GetAbsent(_) | Op::RangeGet(_) | Op::Incr(_) | Op::RangeDelete(_) => {
+                            (0, Vec::new())
                         }
+                    };
+
+                    if let (Op::Get(_) | Op::GetAbsent(_) | Op::Read(_), false) = (op, own) {
+                        outside_reads[key][c].push(OutsideRead { depth: d, op: o });
                     }
+                    info.push(OpInfo {
+                        key,
+                        own_write,
+                        sources,
+                        val,
+                        vals,
+                        range,
+                        observed,
+                    });
                 }
+
+                client_info.push(info);
+                client_write_keys.push(writes);
             }
+
+            op_info.push(client_info);
+            write_keys.push(client_write_keys);
         }
 
-        Self {
-            searched,
-            transactions,
-            kv_rev,
-            searched_cache: HashMap::new(),
+        self.write_sets = write_keys
+            .iter()
+            .map(|client| client.iter().map(|writes| writes.iter().cloned().collect()).collect())
+            .collect();
+        self.own_reads = own_reads;
+        self.op_info = op_info;
+        self.write_keys = write_keys;
+        self.outside_reads = outside_reads;
+    }
+
+    /// Appends `transaction` to `client`, which is either an existing client
+    /// or the next one, so a history recorded as it runs can be checked after
+    /// every transaction. The checker goes back to the transactions
+    /// committed by `commit`, and the next check searches the longer history.
+    ///
+    /// The lookups of values to their writers are extended rather than
+    /// rebuilt, and the verdicts in `searched_cache` are kept as far as they
+    /// still hold. A state known to succeed does not anymore, since the new
+    /// transaction is pending in it. A state known to fail still fails if the
+    /// new transaction writes nothing and touches no new key or client,
+    /// because leaving it out of any serial order gives one of the shorter
+    /// history. Otherwise its writes may satisfy a read which failed before,
+    /// and the cache is cleared. An init transaction committed earlier does
+    /// not write keys first touched later, so it should write every key the
+    /// history is going to use. Likewise a range delete only deletes the keys
+    /// touched by the time its transaction is added.
+    pub fn extend(&mut self, client: usize, transaction: Transaction<K, V>) {
+        assert!(client <= self.transactions.len(), "client {} does not exist", client);
+        self.rewind();
+
+        let (keys, clients) = (self.keys.len(), self.transactions.len());
+        let known: BTreeSet<K> = self.key_names.iter().chain(transaction.touched()).cloned().collect();
+        let transaction = Self::normalize(&transaction, &known);
+        let read_only = !transaction.ops.iter().any(|op| op.is_write());
+        if client == clients {
+            self.transactions.push(Vec::new());
+            self.searched.push(0);
+        }
+        self.transactions[client].push(transaction);
+        self.register((client, self.transactions[client].len() - 1));
+        self.index();
+
+        let unchanged = keys == self.keys.len() && clients == self.transactions.len();
+        self.searched_cache.retain(|_, verdict| read_only && unchanged && !verdict);
+        self.deepest = None;
+    }
+
+    // undoes everything but the transactions committed by `commit`
+    fn rewind(&mut self) {
+        let committed: Vec<usize> = self.order[..self.committed].iter().map(|(index, _)| *index).collect();
+        self.searched = vec![0; self.transactions.len()];
+        self.current = vec![None; self.keys.len()];
+        self.versions = vec![None; self.keys.len()];
+        self.lists = vec![Vec::new(); self.keys.len()];
+        self.order.clear();
+        self.committed = 0;
+        for index in committed.into_iter() {
+            self.commit(index);
         }
     }
 
@@ -53,81 +679,1128 @@ impl<K: Key, V: Value> SerChecker<K, V> {
         self.searched.iter().sum()
     }
 
-    pub fn check(&mut self) -> bool {
-        dbg!(&self.searched);
-        if self.searched_len() == self.target_len() {
-            return true;
-        }
+    fn read_froms(&self, key: &K, val: &V) -> Vec<(usize, usize)> {
+        let mut read_froms: Vec<(usize, usize)> = match self.kv_rev.get(&(key.clone(), val.clone())) {
+            Some(read_froms) => read_froms.iter().cloned().collect(),
+            None => Vec::new(),
+        };
+        read_froms.extend(self.increments.get(key).into_iter().flatten().cloned());
+        read_froms.sort();
 
-        'a: for index in 0..self.transactions.len() {
-            if self.searched[index] < self.transactions[index].len() {
-                let considering_transaction = &self.transactions[index][self.searched[index]];
+        read_froms
+    }
 
-                for op in considering_transaction.ops.iter() {
-                    if let Op::Get(get) = op {
-                        let read_froms = self
-                            .kv_rev
-                            .get(&(get.key.clone(), get.val.clone()))
-                            .unwrap();
+    /// Lists the transactions `get` may read from: the writer of its version
+    /// if it has one, otherwise every writer of its value and every
+    /// increment of its key.
+    pub fn sources(&self, get: &Get<K, V>) -> Vec<(usize, usize)> {
+        let version = match get.version {
+            Some(version) => version,
+            None => return self.read_froms(&get.key, &get.val),
+        };
 
-                        if read_froms.iter().map(|(c, d)| d >= &self.searched[*c]).fold(true, |acc, x| acc && x) {
-                            continue 'a;
-                        }
-                    }
-                }
+        let mut sources: Vec<(usize, usize)> = match self.versions_rev.get(&(get.key.clone(), version)) {
+            Some(writers) => writers.iter().cloned().collect(),
+            None => Vec::new(),
+        };
+        sources.sort();
 
-                for client_index in 0..self.transactions.len() {
-                    let mut bottom = self.searched[client_index];
-                    if client_index == index {
-                        bottom += 1; // exclude the judging transaction
-                    }
+        sources
+    }
+
+    /// Whether transaction `(c, d)` writes `key`, like `Transaction::writes`
+    /// but looked up in the write-sets built by `new`. Aborted transactions
+    /// write nothing.
+    pub fn writes(&self, (c, d): (usize, usize), key: &K) -> bool {
+        self.keys.get(key).is_some_and(|key| self.write_sets[c][d].contains(key))
+    }
 
-                    for index_ in bottom..self.transactions[client_index].len() {
-                        let t = &self.transactions[client_index][index_];
+    /// Builds the dependency graph of the transactions, as `DepGraph`
+    /// describes it. The first transaction committed by `commit`, if any, is
+    /// the init transaction which writes every key before the others.
+    pub fn graph(&self) -> DepGraph<K> {
+        let init = self.order[..self.committed].first().cloned();
 
-                        for op in t.ops.iter() {
-                            if let Op::Get(get) = op {
-                                let key = get.key.clone();
-                                let val = get.val.clone();
+        let mut nodes = Vec::new();
+        let mut edges = HashSet::new();
+        for (c, client) in self.transactions.iter().enumerate() {
+            for d in 0..client.len() {
+                nodes.push((c, d));
+                if d > 0 {
+                    edges.insert(Edge {
+                        from: (c, d - 1),
+                        to: (c, d),
+                        kind: EdgeKind::Session,
+                        key: None,
+                    });
+                }
+            }
+        }
 
-                                if considering_transaction.writes(key.clone()) {
-                                    let read_froms = self
-                                        .kv_rev
-                                        .get(&(get.key.clone(), get.val.clone()))
-                                        .unwrap();
-                                    if read_froms.iter().map(|(c, d)| d < &self.searched[*c]).fold(true, |acc, x| acc && x) {
-                                        // outside cannot read from inside of history if the searching transaction also writes key
-                                        continue 'a;
+        // aborted transactions have been emptied and compare-and-sets expanded
+        let mut read_froms = Vec::new();
+        let mut writers: HashMap<K, HashSet<(usize, usize)>> = HashMap::new();
+        for (c, client) in self.transactions.iter().enumerate() {
+            for (d, t) in client.iter().enumerate() {
+                for (op, own) in t.ops.iter().zip(self.own_reads[c][d].iter()) {
+                    match op {
+                        Op::Get(_) if *own => {}
+                        Op::Get(get) => {
+                            if let [source] = self.sources(get)[..] {
+                                if source != (c, d) {
+                                    read_froms.push((source, (c, d), get.key.clone()));
+                                }
+                            }
+                        }
+                        Op::Set(set) => {
+                            writers.entry(set.key.clone()).or_default().insert((c, d));
+                        }
+                        Op::GetAbsent(_) if *own => {}
+                        Op::GetAbsent(get) => {
+                            if let Some(sources) = self.deletes.get(&get.key) {
+                                if sources.len() == 1 {
+                                    let source = *sources.iter().next().unwrap();
+                                    if source != (c, d) {
+                                        read_froms.push((source, (c, d), get.key.clone()));
+                                    }
+                                }
+                            }
+                        }
+                        Op::Delete(delete) => {
+                            writers.entry(delete.key.clone()).or_default().insert((c, d));
+                        }
+                        Op::Incr(incr) => {
+                            writers.entry(incr.key.clone()).or_default().insert((c, d));
+                        }
+                        Op::RangeGet(_) if *own => {}
+                        Op::RangeGet(range) => {
+                            for (key, val) in range.observed.iter() {
+                                if let [source] = self.sources(&Get::new(key.clone(), val.clone()))[..] {
+                                    if source != (c, d) {
+                                        read_froms.push((source, (c, d), key.clone()));
                                     }
                                 }
                             }
                         }
+                        Op::Append(_) | Op::Read(_) | Op::Cas(_) | Op::RangeDelete(_) => {}
                     }
                 }
+            }
+        }
 
-                self.searched[index] += 1;
-                match self.searched_cache.get(&self.searched) {
-                    Some(value) => {
-                        if *value {
-                            return true;
-                        } else {
-                            self.searched[index] -= 1;
+        let mut version_order = HashSet::new();
+        for (key, ws) in writers.iter() {
+            for w in ws.iter() {
+                if let Some(init) = init {
+                    if *w != init {
+                        version_order.insert((init, *w, key.clone()));
+                    }
+                }
+                for other in ws.iter() {
+                    if w.0 == other.0 && w.1 < other.1 {
+                        version_order.insert((*w, *other, key.clone()));
+                    }
+                }
+            }
+        }
+        for (source, reader, key) in read_froms.iter() {
+            if writers.get(key).is_some_and(|ws| ws.contains(reader)) {
+                version_order.insert((*source, *reader, key.clone()));
+            }
+        }
+
+        for (source, reader, key) in read_froms.iter() {
+            edges.insert(Edge {
+                from: *source,
+                to: *reader,
+                kind: EdgeKind::WriteRead,
+                key: Some(key.clone()),
+            });
+
+            for (from, to, k) in version_order.iter() {
+                if from == source && k == key && to != reader {
+                    edges.insert(Edge {
+                        from: *reader,
+                        to: *to,
+                        kind: EdgeKind::ReadWrite,
+                        key: Some(key.clone()),
+                    });
+                }
+            }
+        }
+        for (from, to, key) in version_order.into_iter() {
+            edges.insert(Edge {
+                from,
+                to,
+                kind: EdgeKind::WriteWrite,
+                key: Some(key),
+            });
+        }
+
+        let mut edges: Vec<Edge<K>> = edges.into_iter().collect();
+        edges.sort_by_key(|e| (e.from, e.to, e.kind as usize, format!("{:?}", e.key)));
+
+        DepGraph { nodes, edges, init }
+    }
+
+    /// The key numbered `id`, the index of its entry in `current`,
+    /// `versions` and `lists`.
+    pub fn key(&self, id: usize) -> &K {
+        &self.key_names[id]
+    }
+
+    /// The value numbered `id`, as `current` and `lists` hold it.
+    pub fn value(&self, id: u32) -> &V {
+        &self.values[id as usize]
+    }
+
+    fn state(&self) -> State {
+        (self.searched.clone(), self.current.clone(), self.versions.clone(), self.lists.clone())
+    }
+
+    fn restore(&mut self, state: State) {
+        (self.searched, self.current, self.versions, self.lists) = state;
+    }
+
+    // the list stored in `key` after committing the next transaction of `index`
+    fn appended(&self, index: usize, key: usize) -> Vec<u32> {
+        let t = &self.transactions[index][self.searched[index]];
+        let mut list = self.lists[key].clone();
+        for (op, info) in t.ops.iter().zip(self.op_info[index][self.searched[index]].iter()) {
+            if let (Op::Append(_), true) = (op, info.key == key) {
+                list.push(info.val);
+            }
+        }
+
+        list
+    }
+
+    fn blocker(&self, index: usize) -> Option<Blocker<K, V>> {
+        let t = &self.transactions[index][self.searched[index]];
+        let info = &self.op_info[index][self.searched[index]];
+
+        Some(match self.block(index)? {
+            Block::Read(o) => match &t.ops[o] {
+                Op::Get(get) => Blocker::ReadFrom {
+                    key: get.key.clone(),
+                    val: get.val.clone(),
+                    writers: info[o].sources.clone(),
+                },
+                Op::GetAbsent(get) => Blocker::ReadAbsent {
+                    key: get.key.clone(),
+                    deleters: info[o].sources.clone(),
+                },
+                Op::Read(read) => Blocker::ReadList {
+                    key: read.key.clone(),
+                    vals: read.vals.clone(),
+                },
+                Op::Cas(cas) => Blocker::FailedCas {
+                    key: cas.key.clone(),
+                    val: cas.expected.clone(),
+                },
+                Op::RangeGet(range) => Blocker::ReadRange {
+                    start: range.start.clone(),
+                    end: range.end.clone(),
+                    observed: range.observed.clone(),
+                },
+                Op::Incr(incr) => Blocker::Increment {
+                    key: incr.key.clone(),
+                    val: self.counted(index, o).0,
+                },
+                Op::Set(_) | Op::Append(_) | Op::Delete(_) | Op::RangeDelete(_) => unreachable!(),
+            },
+            Block::Own(o, w) => match (&t.ops[o], &t.ops[w]) {
+                (Op::Get(get), Op::Set(set)) => Blocker::OwnWrite {
+                    key: get.key.clone(),
+                    val: get.val.clone(),
+                    written: set.val.clone(),
+                },
+                (op, _) => Blocker::OwnDelete { key: op.key().clone() },
+            },
+            Block::Hide(c, d, o) => match &self.transactions[c][d].ops[o] {
+                Op::Get(get) => Blocker::Overwrite {
+                    key: get.key.clone(),
+                    val: get.val.clone(),
+                    reader: (c, d),
+                },
+                Op::Read(read) => Blocker::Append {
+                    key: read.key.clone(),
+                    reader: (c, d),
+                },
+                Op::GetAbsent(get) => Blocker::Undelete {
+                    key: get.key.clone(),
+                    reader: (c, d),
+                },
+                Op::Set(_)
+                | Op::Append(_)
+                | Op::Cas(_)
+                | Op::Delete(_)
+                | Op::RangeGet(_)
+                | Op::Incr(_)
+                | Op::RangeDelete(_) => unreachable!(),
+            },
+            Block::After(c, d) => Blocker::Precedence { earlier: (c, d) },
+        })
+    }
+
+    fn block(&self, index: usize) -> Option<Block> {
+        if let Some(block) = self.read_block(index) {
+            return Some(block);
+        }
+        // a lost write hides nothing, so only the reads are checked
+        if self.last_write_wins {
+            return None;
+        }
+
+        #[cfg(test)]
+        if self.naive {
+            return self.naive_outside_block(index);
+        }
+
+        // the first pending reader hidden by any key the transaction writes
+        let mut found: Option<(usize, usize, usize)> = None;
+        for &key in self.write_keys[index][self.searched[index]].iter() {
+            for (client_index, reads) in self.outside_reads[key].iter().enumerate() {
+                let mut bottom = self.searched[client_index];
+                if client_index == index {
+                    bottom += 1; // exclude the judging transaction
+                }
+
+                let pending = reads.partition_point(|read| read.depth < bottom);
+                for read in reads[pending..].iter() {
+                    let position = (client_index, read.depth, read.op);
+                    if found.is_some_and(|first| first <= position) {
+                        break;
+                    }
+                    if self.hides(index, position) {
+                        found = Some(position);
+                        break;
+                    }
+                }
+            }
+        }
+
+        found.map(|(c, d, o)| Block::Hide(c, d, o))
+    }
+
+    // whether some read of the next transaction of `index` disagrees with
+    // the current state or with the transaction's own writes
+    fn read_block(&self, index: usize) -> Option<Block> {
+        if let Some(earlier) = self.after.get(&(index, self.searched[index])) {
+            if let Some(&(c, d)) = earlier.iter().find(|(c, d)| self.searched[*c] <= *d) {
+                return Some(Block::After(c, d));
+            }
+        }
+
+        let considering_transaction = &self.transactions[index][self.searched[index]];
+        let infos = &self.op_info[index][self.searched[index]];
+
+        // reads observe the writes made earlier in the same transaction
+        let mut appended: HashMap<usize, Vec<u32>> = HashMap::new();
+        for (o, (op, info)) in considering_transaction.ops.iter().zip(infos.iter()).enumerate() {
+            let written = info.own_write.map(|w| match &considering_transaction.ops[w] {
+                Op::Set(set) => Some((set, infos[w].val)),
+                _ => None,
+            });
+            // the sum left by an earlier increment, which succeeded or the
+            // transaction would have been blocked there
+            let counter = info
+                .own_write
+                .filter(|&w| matches!(considering_transaction.ops[w], Op::Incr(_)))
+                .and_then(|w| self.counted(index, w).1);
+
+            match op {
+                Op::Set(_) | Op::Delete(_) | Op::RangeDelete(_) => {}
+                Op::Incr(_) => {
+                    if self.counted(index, o).1.is_none() {
+                        return Some(Block::Read(o));
+                    }
+                }
+                Op::Get(get) if counter.is_some() => {
+                    if get.version.is_some() || counter.as_ref() != Some(&get.val) {
+                        return Some(Block::Read(o));
+                    }
+                }
+                Op::GetAbsent(_) if counter.is_some() => return Some(Block::Own(o, info.own_write?)),
+                Op::Cas(cas) if counter.is_some() => {
+                    if counter.as_ref() == Some(&cas.expected) {
+                        return Some(Block::Read(o));
+                    }
+                }
+                Op::Append(_) => {
+                    appended
+                        .entry(info.key)
+                        .or_insert_with(|| self.lists[info.key].clone())
+                        .push(info.val);
+                }
+                Op::Get(get) => match written {
+                    Some(Some((set, val))) => {
+                        let other_version = get.version.is_some() && set.version.is_some() && get.version != set.version;
+                        if val != info.val || other_version {
+                            return Some(Block::Own(o, info.own_write?));
+                        }
+                    }
+                    Some(None) => return Some(Block::Own(o, info.own_write?)),
+                    None => {
+                        let other_version = get.version.is_some() && self.versions[info.key] != get.version;
+                        if self.current[info.key] != Some(info.val) || other_version {
+                            return Some(Block::Read(o));
                         }
                     }
+                },
+                Op::GetAbsent(_) => match written {
+                    Some(Some(_)) => return Some(Block::Own(o, info.own_write?)),
+                    Some(None) => {}
                     None => {
-                        if self.check() {
-                            self.searched_cache.insert(self.searched.clone(), true);
+                        if self.current[info.key].is_some() {
+                            return Some(Block::Read(o));
+                        }
+                    }
+                },
+                Op::Read(_) => {
+                    let list = appended.get(&info.key).unwrap_or(&self.lists[info.key]);
+                    if *list != info.vals {
+                        return Some(Block::Read(o));
+                    }
+                }
+                // successful ones have been expanded into a get and a set
+                Op::Cas(_) => {
+                    let held = match written {
+                        Some(set) => set.map(|(_, val)| val),
+                        None => self.current[info.key],
+                    };
+                    if held == Some(info.val) {
+                        return Some(Block::Read(o));
+                    }
+                }
+                Op::RangeGet(_) => {
+                    let present = info.range.iter().filter_map(|&key| {
+                        // the last earlier set or delete of the key in the transaction
+                        let own = considering_transaction.ops[..o]
+                            .iter()
+                            .zip(infos.iter())
+                            .enumerate()
+                            .rev()
+                            .find_map(|(w, (op, info))| match op {
+                                Op::Set(_) if info.key == key => Some(Some(info.val)),
+                                Op::Delete(_) if info.key == key => Some(None),
+                                // a sum no read observes if it is not numbered
+                                Op::Incr(_) if info.key == key => Some(
+                                    self.counted(index, w).1.map(|val| self.value_ids.get(&val).cloned().unwrap_or(u32::MAX)),
+                                ),
+                                _ => None,
+                            });
+                        let held = own.unwrap_or(self.current[key]);
+                        held.filter(|val| *val != 0).map(|val| (key, val))
+                    });
+                    if !present.eq(info.observed.iter().cloned()) {
+                        return Some(Block::Read(o));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    // the value the key of operation `o` of the next transaction of `index`
+    // holds before it, and what adding the delta of the operation, an
+    // increment, makes of it
+    fn counted(&self, index: usize, o: usize) -> (V, Option<V>) {
+        let t = &self.transactions[index][self.searched[index]];
+        let info = &self.op_info[index][self.searched[index]][o];
+
+        let held = match info.own_write.map(|w| (w, &t.ops[w])) {
+            Some((_, Op::Set(set))) => set.val.clone(),
+            Some((w, Op::Incr(_))) => self.counted(index, w).1.unwrap_or_else(V::initial_value),
+            Some(_) => V::initial_value(),
+            None => self.current[info.key].map_or_else(V::initial_value, |id| self.values[id as usize].clone()),
+        };
+        let sum = match &t.ops[o] {
+            Op::Incr(incr) => held.increment(incr.delta),
+            _ => None,
+        };
+
+        (held, sum)
+    }
+
+    // whether writing the next transaction of `index` hides what operation
+    // `o` of transaction `(c, d)` observes
+    fn hides(&self, index: usize, (c, d, o): (usize, usize, usize)) -> bool {
+        let info = &self.op_info[c][d][o];
+        let sources_searched = || info.sources.iter().all(|(c, d)| d < &self.searched[*c]);
+
+        match &self.transactions[c][d].ops[o] {
+            // outside cannot read from inside of history if the searching transaction also writes key
+            Op::Get(_) | Op::GetAbsent(_) => sources_searched(),
+            // lists only grow, so a reader is lost once the list stops being
+            // a prefix of what it observed
+            Op::Read(_) => {
+                info.vals.starts_with(&self.lists[info.key]) && !info.vals.starts_with(&self.appended(index, info.key))
+            }
+            // range reads are left to the search
+            Op::Set(_)
+            | Op::Append(_)
+            | Op::Cas(_)
+            | Op::Delete(_)
+            | Op::RangeGet(_)
+            | Op::Incr(_)
+            | Op::RangeDelete(_) => false,
+        }
+    }
+
+    // whether some pending read of one of `keys` observes what the key does
+    // not hold, while none of its sources is left which could commit before
+    // it: they are all committed already, or follow the reader in its own
+    // client. No order extends the current one then.
+    fn doomed(&self, keys: &[usize]) -> bool {
+        #[cfg(test)]
+        if self.unpruned {
+            return false;
+        }
+
+        keys.iter().any(|&key| {
+            self.outside_reads[key].iter().enumerate().any(|(c, reads)| {
+                let pending = reads.partition_point(|read| read.depth < self.searched[c]);
+                reads[pending..].iter().any(|read| {
+                    let info = &self.op_info[c][read.depth][read.op];
+                    let placeable = || {
+                        info.sources
+                            .iter()
+                            .any(|&(wc, wd)| wd >= self.searched[wc] && !(wc == c && wd >= read.depth))
+                    };
+
+                    match &self.transactions[c][read.depth].ops[read.op] {
+                        Op::Get(get) => {
+                            let held = self.current[key] == Some(info.val)
+                                && (get.version.is_none() || self.versions[key] == get.version);
+                            !held && !placeable()
+                        }
+                        Op::GetAbsent(_) => self.current[key].is_some() && !placeable(),
+                        // lists only grow
+                        Op::Read(_) => !info.vals.starts_with(&self.lists[key]),
+                        Op::Set(_)
+                        | Op::Append(_)
+                        | Op::Cas(_)
+                        | Op::Delete(_)
+                        | Op::RangeGet(_)
+                        | Op::Incr(_)
+                        | Op::RangeDelete(_) => false,
+                    }
+                })
+            })
+        })
+    }
+
+    #[cfg(test)]
+    fn naive_outside_block(&self, index: usize) -> Option<Block> {
+        let considering = (index, self.searched[index]);
+
+        for client_index in 0..self.transactions.len() {
+            let mut bottom = self.searched[client_index];
+            if client_index == index {
+                bottom += 1; // exclude the judging transaction
+            }
+
+            for index_ in bottom..self.transactions[client_index].len() {
+                let t = &self.transactions[client_index][index_];
+                let own_reads = &self.own_reads[client_index][index_];
+
+                for (o, (op, own)) in t.ops.iter().zip(own_reads.iter()).enumerate() {
+                    if *own {
+                        continue;
+                    }
+
+                    let hidden = match op {
+                        Op::Get(get) => {
+                            self.writes(considering, &get.key)
+                                && self.sources(get).iter().all(|(c, d)| d < &self.searched[*c])
+                        }
+                        Op::Read(read) => {
+                            let info = &self.op_info[client_index][index_][o];
+                            self.writes(considering, &read.key)
+                                && info.vals.starts_with(&self.lists[info.key])
+                                && !info.vals.starts_with(&self.appended(index, info.key))
+                        }
+                        Op::GetAbsent(get) => {
+                            let mut deleters: Vec<(usize, usize)> =
+                                self.deletes.get(&get.key).into_iter().flatten().cloned().collect();
+                            deleters.sort();
+                            self.writes(considering, &get.key)
+                                && deleters.iter().all(|(c, d)| d < &self.searched[*c])
+                        }
+                        Op::Set(_)
+                        | Op::Append(_)
+                        | Op::Cas(_)
+                        | Op::Delete(_)
+                        | Op::RangeGet(_)
+                        | Op::Incr(_)
+                        | Op::RangeDelete(_) => false,
+                    };
+                    if hidden {
+                        return Some(Block::Hide(client_index, index_, o));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn apply(&mut self, index: usize) -> Undo {
+        let mut undo = Vec::new();
+        let t = &self.transactions[index][self.searched[index]];
+        for (op, info) in t.ops.iter().zip(self.op_info[index][self.searched[index]].iter()) {
+            let key_index = info.key;
+            match op {
+                Op::Set(set) => {
+                    if self.last_write_wins && set.version.is_some() && set.version < self.versions[key_index] {
+                        continue;
+                    }
+                    let version = core::mem::replace(&mut self.versions[key_index], set.version);
+                    undo.push(Change::Set(key_index, self.current[key_index].replace(info.val), version));
+                }
+                Op::Append(_) => {
+                    self.lists[key_index].push(info.val);
+                    undo.push(Change::Append(key_index));
+                }
+                Op::Delete(_) => {
+                    undo.push(Change::Set(key_index, self.current[key_index].take(), self.versions[key_index].take()));
+                }
+                // a sum first reached by this search is numbered now
+                Op::Incr(incr) => {
+                    let held = self.current[key_index].map_or_else(V::initial_value, |id| self.values[id as usize].clone());
+                    let val = match held.increment(incr.delta) {
+                        Some(val) => val,
+                        None => continue,
+                    };
+                    let id = match self.value_ids.get(&val) {
+                        Some(id) => *id,
+                        None => {
+                            self.values.push(val.clone());
+                            self.value_ids.insert(val, self.values.len() as u32 - 1);
+                            self.values.len() as u32 - 1
+                        }
+                    };
+                    undo.push(Change::Set(key_index, self.current[key_index].replace(id), self.versions[key_index].take()));
+                }
+                Op::Get(_) | Op::Read(_) | Op::Cas(_) | Op::GetAbsent(_) | Op::RangeGet(_) | Op::RangeDelete(_) => {}
+            }
+        }
+
+        undo
+    }
+
+    fn undo(&mut self, undo: Undo) {
+        for change in undo.into_iter().rev() {
+            match change {
+                Change::Set(key_index, val, version) => {
+                    self.current[key_index] = val;
+                    self.versions[key_index] = version;
+                }
+                Change::Append(key_index) => {
+                    self.lists[key_index].pop();
+                }
+            }
+        }
+    }
+
+    /// Makes transaction `later` wait until transaction `earlier` of another
+    /// client is committed, e.g. when a session is split into a client per
+    /// transaction and only some of its order has to be kept. States which
+    /// were found serializable may no longer be, so their verdicts are
+    /// dropped from the cache.
+    pub fn precede(&mut self, earlier: (usize, usize), later: (usize, usize)) {
+        self.after.entry(later).or_default().push(earlier);
+        self.searched_cache.retain(|_, verdict| !verdict);
+        self.deepest = None;
+    }
+
+    /// Commits the next transaction of `index` before searching, e.g. an
+    /// init transaction that has to precede everything else.
+    pub fn commit(&mut self, index: usize) {
+        let _ = self.apply(index);
+        self.order.push((index, self.searched[index]));
+        self.searched[index] += 1;
+        self.committed += 1;
+    }
+
+    /// Commits the next transaction of `index` like `commit`, but only if
+    /// it can extend the order, and tells whether it could.
+    pub fn try_commit(&mut self, index: usize) -> bool {
+        if self.searched[index] >= self.transactions[index].len() || self.block(index).is_some() {
+            return false;
+        }
+
+        self.commit(index);
+        true
+    }
+
+    /// Commits the next transaction of `index` if it observes the current
+    /// state, like `try_commit` but without making sure it leaves pending
+    /// reads satisfiable, so replaying a given order takes linear time.
+    pub fn try_replay(&mut self, index: usize) -> bool {
+        if self.searched[index] >= self.transactions[index].len() || self.read_block(index).is_some() {
+            return false;
+        }
 
-                            return true;
-                        } else {
-                            self.searched_cache.insert(self.searched.clone(), false);
+        self.commit(index);
+        true
+    }
+
+    fn record_deepest(&mut self) {
+        if let Some(anomaly) = &self.deepest {
+            if anomaly.order.len() >= self.order.len() {
+                return;
+            }
+        }
+
+        self.deepest = Some(self.anomaly());
+    }
+
+    // the current prefix and why every unfinished client cannot extend it
+    fn anomaly(&self) -> Anomaly<K, V> {
+        let mut blocked = Vec::new();
+        for index in 0..self.transactions.len() {
+            if self.searched[index] < self.transactions[index].len() {
+                if let Some(blocker) = self.blocker(index) {
+                    blocked.push(((index, self.searched[index]), blocker));
+                }
+            }
+        }
+
+        Anomaly {
+            order: self.order.clone(),
+            blocked,
+        }
+    }
+
+    // starts from the transactions committed by `commit`, whatever an
+    // earlier search left behind
+    fn search(&mut self) -> bool {
+        self.rewind();
+        if self.searched_len() == self.target_len() {
+            return true;
+        }
+        self.record_deepest();
+        let keys: Vec<usize> = (0..self.keys.len()).collect();
+        if self.doomed(&keys) {
+            self.searched_cache.insert(self.state(), false);
+            return false;
+        }
+
+        // every frame stands for a search state, `next` is the first client
+        // not tried yet from it and `entered` is the move leading into it
+        let mut rng = Rng::new(match self.search_order {
+            SearchOrder::Random(seed) => seed,
+            SearchOrder::IndexOrder | SearchOrder::MostConstrainedFirst => 0,
+        });
+        let mut stack = vec![Frame {
+            next: 0,
+            clients: Vec::new(),
+            entered: None,
+        }];
+        while let Some(frame) = stack.last_mut() {
+            // a transaction which writes nothing leaves the state as it is, so
+            // committing it as soon as it can be committed loses no order and
+            // the other candidates need not be tried
+            let read_only = if frame.next == 0 {
+                (0..self.transactions.len()).find(|&index| {
+                    self.searched[index] < self.transactions[index].len()
+                        && self.write_keys[index][self.searched[index]].is_empty()
+                        && self.block(index).is_none()
+                })
+            } else {
+                None
+            };
+            if frame.next == 0 && read_only.is_none() {
+                frame.clients = self.ranked(&mut rng);
+            }
+            let candidate = read_only.map(|index| (index, self.transactions.len())).or_else(|| {
+                (frame.next..self.transactions.len())
+                    .map(|position| (frame.clients.get(position).cloned().unwrap_or(position), position + 1))
+                    .find(|&(index, _)| {
+                        self.searched[index] < self.transactions[index].len() && self.block(index).is_none()
+                    })
+            });
+
+            match candidate {
+                Some((index, next)) => {
+                    frame.next = next;
+
+                    let undo = self.apply(index);
+                    self.order.push((index, self.searched[index]));
+                    self.searched[index] += 1;
+                    self.stats.nodes_explored += 1;
+                    self.stats.max_depth = self.stats.max_depth.max(stack.len());
+
+                    let cached = self.searched_cache.get(&self.state());
+                    if cached.is_some() {
+                        self.stats.cache_hits += 1;
+                    } else {
+                        self.stats.cache_misses += 1;
+                    }
+                    match cached {
+                        Some(true) => return true,
+                        Some(false) => {
                             self.searched[index] -= 1;
+                            self.order.pop();
+                            self.undo(undo);
+                        }
+                        None => {
+                            if self.searched_len() == self.target_len() {
+                                self.searched_cache.insert(self.state(), true);
+                                return true;
+                            }
+                            self.record_deepest();
+
+                            // only the keys just written can leave a read unsatisfiable
+                            if self.doomed(&self.write_keys[index][self.searched[index] - 1]) {
+                                self.searched_cache.insert(self.state(), false);
+                                self.searched[index] -= 1;
+                                self.order.pop();
+                                self.undo(undo);
+                                continue;
+                            }
+                            stack.push(Frame {
+                                next: 0,
+                                clients: Vec::new(),
+                                entered: Some((index, undo)),
+                            });
+
+                            if self.exhausted() {
+                                self.gave_up = true;
+                                while let Some(Frame { entered: Some((index, undo)), .. }) = stack.pop() {
+                                    self.searched[index] -= 1;
+                                    self.order.pop();
+                                    self.undo(undo);
+                                }
+                                return false;
+                            }
                         }
                     }
                 }
+                None => {
+                    if let Some(Frame { entered: Some((index, undo)), .. }) = stack.pop() {
+                        self.searched_cache.insert(self.state(), false);
+                        self.searched[index] -= 1;
+                        self.order.pop();
+                        self.undo(undo);
+                    }
+                }
             }
         }
 
         false
     }
+
+    // the clients in the order `search_order` tries them, empty for the
+    // order of their indices
+    fn ranked(&self, rng: &mut Rng) -> Vec<usize> {
+        let mut clients: Vec<usize> = (0..self.transactions.len()).collect();
+        match self.search_order {
+            SearchOrder::IndexOrder => return Vec::new(),
+            SearchOrder::MostConstrainedFirst => {
+                clients.sort_by_key(|&index| core::cmp::Reverse(self.outside_reads_of(index)));
+            }
+            SearchOrder::Random(_) => {
+                for i in (1..clients.len()).rev() {
+                    clients.swap(i, rng.below(i + 1));
+                }
+            }
+        }
+
+        clients
+    }
+
+    // how many operations of the next transaction of `index` read what
+    // other transactions wrote
+    fn outside_reads_of(&self, index: usize) -> usize {
+        let d = self.searched[index];
+        match self.transactions[index].get(d) {
+            Some(t) => t
+                .ops
+                .iter()
+                .zip(self.own_reads[index][d].iter())
+                .filter(|(op, own)| !op.is_write() && !**own)
+                .count(),
+            None => 0,
+        }
+    }
+
+    // whether the search has run out of time or nodes
+    fn exhausted(&self) -> bool {
+        #[cfg(feature = "std")]
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return true;
+        }
+
+        self.node_limit.is_some_and(|limit| self.stats.nodes_explored > limit)
+    }
+
+    /// Searches for a serial order of all transactions. On success the order
+    /// is returned as `(client, depth)` pairs, otherwise the deepest prefix
+    /// reached and the reasons why no transaction could extend it.
+    pub fn check_with_witness(&mut self) -> Result<Vec<(usize, usize)>, Anomaly<K, V>> {
+        if self.search() {
+            Ok(self.order.clone())
+        } else {
+            Err(self.deepest.clone().unwrap_or_default())
+        }
+    }
+
+    /// Lists the serial orders of all transactions, at most `max` of them,
+    /// as `(client, depth)` pairs like `check_with_witness`. Where `check`
+    /// stops at the first order, this walks every branch which can still
+    /// complete, so it takes time in the number of orders, which grows
+    /// factorially with the number of concurrent transactions. Only meant
+    /// for small histories, and `max` bounds the cost for the others.
+    pub fn all_orders(&mut self, max: usize) -> Vec<Vec<(usize, usize)>> {
+        self.rewind();
+        let mut orders = Vec::new();
+        if max > 0 {
+            self.enumerate(max, &mut orders);
+        }
+
+        orders
+    }
+
+    // extends the current order in every possible way, and remembers the
+    // states from which nothing completes
+    fn enumerate(&mut self, max: usize, orders: &mut Vec<Vec<(usize, usize)>>) {
+        if self.searched_len() == self.target_len() {
+            orders.push(self.order.clone());
+            return;
+        }
+
+        for index in 0..self.transactions.len() {
+            if orders.len() >= max {
+                return;
+            }
+            if self.searched[index] >= self.transactions[index].len() || self.block(index).is_some() {
+                continue;
+            }
+
+            let undo = self.apply(index);
+            self.order.push((index, self.searched[index]));
+            self.searched[index] += 1;
+            self.stats.nodes_explored += 1;
+
+            let found = orders.len();
+            let dead = self.searched_cache.get(&self.state()) == Some(false)
+                || self.doomed(&self.write_keys[index][self.searched[index] - 1]);
+            if !dead {
+                self.enumerate(max, orders);
+                if orders.len() == found {
+                    self.searched_cache.insert(self.state(), false);
+                }
+            }
+
+            self.searched[index] -= 1;
+            self.order.pop();
+            self.undo(undo);
+        }
+    }
+
+    /// Returns what the searches of this checker have done so far.
+    pub fn stats(&self) -> &SerCheckerStats {
+        &self.stats
+    }
+
+    /// Tells whether the history is serializable. Every search starts over
+    /// from the transactions committed by `commit`, so calling it again
+    /// gives the same answer, only faster as the cache remembers states.
+    pub fn check(&mut self) -> bool {
+        self.rewind();
+        if self.fast_reject() == Some(false) {
+            return false;
+        }
+
+        self.check_with_witness().is_ok()
+    }
+
+    /// Looks for a cycle of dependencies which every serial order has to
+    /// respect: the session order of a client, and reads of a value which
+    /// only one transaction writes. Returns `Some(false)` if there is one or
+    /// some read can never be satisfied, `Some(true)` if nothing is left to
+    /// search, and `None` if the full search is needed.
+    pub fn fast_reject(&self) -> Option<bool> {
+        if self.searched_len() == self.target_len() {
+            return Some(true);
+        }
+
+        let mut successors: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+        let mut predecessors: HashMap<(usize, usize), usize> = HashMap::new();
+        for (c, client) in self.transactions.iter().enumerate() {
+            for (d, t) in client.iter().enumerate().skip(self.searched[c]) {
+                predecessors.entry((c, d)).or_insert(0);
+                if d > self.searched[c] {
+                    successors.entry((c, d - 1)).or_default().push((c, d));
+                    *predecessors.entry((c, d)).or_insert(0) += 1;
+                }
+
+                for ((op, own), info) in t.ops.iter().zip(self.own_reads[c][d].iter()).zip(self.op_info[c][d].iter()) {
+                    if let (Op::Get(_), false) = (op, own) {
+                        let writers: Vec<(usize, usize)> =
+                            info.sources.iter().cloned().filter(|writer| *writer != (c, d)).collect();
+                        match writers[..] {
+                            [] => return Some(false),
+                            [(wc, wd)] if wd >= self.searched[wc] => {
+                                successors.entry((wc, wd)).or_default().push((c, d));
+                                *predecessors.entry((c, d)).or_insert(0) += 1;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        // every transaction left after removing the ones without pending
+        // predecessors lies on a cycle or behind one
+        let mut ready: Vec<(usize, usize)> = predecessors.iter().filter(|(_, n)| **n == 0).map(|(t, _)| *t).collect();
+        let mut removed = 0;
+        while let Some(t) = ready.pop() {
+            removed += 1;
+            for next in successors.get(&t).into_iter().flatten() {
+                let n = predecessors.get_mut(next).unwrap();
+                *n -= 1;
+                if *n == 0 {
+                    ready.push(*next);
+                }
+            }
+        }
+
+        if removed < predecessors.len() {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Searches like `check_with_witness`, but breadth first, so on failure
+    /// the reported prefix is a shortest one which cannot be extended. Every
+    /// search state is visited, which makes it only suitable for debugging.
+    pub fn check_with_shortest_witness(&mut self) -> Result<Vec<(usize, usize)>, Anomaly<K, V>> {
+        let base = (self.state(), self.order.clone(), self.committed);
+        let target_len = self.target_len();
+
+        let mut shortest: Option<Anomaly<K, V>> = None;
+        let mut visited = HashSet::new();
+        let mut level = vec![Vec::new()];
+        let mut result = None;
+
+        while !level.is_empty() && result.is_none() {
+            let mut next_level = Vec::new();
+
+            for moves in level.into_iter() {
+                self.restore(base.0.clone());
+                self.order = base.1.clone();
+                self.committed = base.2;
+                for index in moves.iter() {
+                    self.commit(*index);
+                }
+
+                if self.searched_len() == target_len {
+                    result = Some(Ok(self.order.clone()));
+                    break;
+                }
+
+                let candidates: Vec<usize> = (0..self.transactions.len())
+                    .filter(|&index| self.searched[index] < self.transactions[index].len() && self.block(index).is_none())
+                    .collect();
+                if candidates.is_empty() {
+                    if shortest.is_none() {
+                        shortest = Some(self.anomaly());
+                    }
+                    continue;
+                }
+
+                for index in candidates {
+                    let undo = self.apply(index);
+                    self.searched[index] += 1;
+                    if visited.insert(self.state()) {
+                        let mut moves = moves.clone();
+                        moves.push(index);
+                        next_level.push(moves);
+                    }
+                    self.searched[index] -= 1;
+                    self.undo(undo);
+                }
+            }
+
+            level = next_level;
+        }
+
+        self.restore(base.0);
+        self.order = base.1;
+        self.committed = base.2;
+
+        result.unwrap_or_else(|| Err(shortest.unwrap_or_default()))
+    }
+
+    /// Checks serializability like `check`, but gives up and returns `None`
+    /// if the search takes longer than `timeout`. Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn check_with_timeout(&mut self, timeout: Duration) -> Option<bool> {
+        self.deadline = Some(Instant::now() + timeout);
+        self.gave_up = false;
+        let serializable = self.search();
+        self.deadline = None;
+
+        if self.gave_up {
+            None
+        } else {
+            Some(serializable)
+        }
+    }
+
+    /// Checks serializability like `check`, but gives up and returns `None`
+    /// once the search explores more than `max_nodes` states.
+    pub fn check_with_node_limit(&mut self, max_nodes: u64) -> Option<bool> {
+        self.node_limit = Some(self.stats.nodes_explored.saturating_add(max_nodes));
+        self.gave_up = false;
+        let serializable = self.search();
+        self.node_limit = None;
+
+        if self.gave_up {
+            None
+        } else {
+            Some(serializable)
+        }
+    }
+
+    /// Checks serializability like `check`, but searches the branches of
+    /// every possible first transaction on the rayon thread pool. The
+    /// branches share the states which are known to fail. Requires the
+    /// `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn check_parallel(&mut self) -> bool
+    where
+        K: Send + Sync,
+        V: Send + Sync,
+    {
+        self.rewind();
+        if self.searched_len() == self.target_len() {
+            return true;
+        }
+
+        let shared = Arc::new(Mutex::new(HashSet::new()));
+        let candidates: Vec<usize> = (0..self.transactions.len())
+            .filter(|&index| self.searched[index] < self.transactions[index].len() && self.block(index).is_none())
+            .collect();
+
+        // every branch numbers the sums of increments on its own, so states
+        // holding them mean different things in different branches
+        let checker = &*self;
+        let share = self.increments.is_empty();
+        candidates.into_par_iter().any(|index| {
+            let mut branch = checker.clone();
+            if share {
+                branch.searched_cache.shared = Some(shared.clone());
+            }
+            branch.commit(index);
+            branch.check()
+        })
+    }
 }
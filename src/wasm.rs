@@ -0,0 +1,68 @@
+//! The entry point of the checker for the browser, e.g. a playground which
+//! pastes a history and shows the verdict. It takes and returns JSON
+//! strings, so the only thing crossing the boundary to JavaScript is text.
+//!
+//! The history is the JSON serialization of `History<String, u64>`, and the
+//! level is one of the names the command line tool takes: `ser`, `si`,
+//! `gsi`, `prefix`, `causal`, `cs`, `mav`, `rc` or `ru`. The verdict is an
+//! object with the level and whether it `holds`. For `ser`, `si`, `gsi` and
+//! `prefix` a passing history comes with the serial `witness` as
+//! `[client, depth]` pairs, and a failing one with the `anomaly` of
+//! `History::classify_anomaly` if it is a known one. A history which cannot
+//! be read or an unknown level gives an object with just an `error`.
+
+use crate::transaction::History;
+use alloc::string::{String, ToString};
+use serde_json::{json, Value};
+
+/// Checks the JSON history `json` against the isolation level `level` and
+/// returns the verdict as JSON.
+///
+/// ```
+/// use ergosum::wasm::check_history_json;
+///
+/// let verdict = check_history_json(r#"{"transactions": []}"#, "ser");
+/// assert_eq!(verdict, r#"{"holds":true,"level":"ser","witness":[]}"#);
+/// ```
+pub fn check_history_json(json: &str, level: &str) -> String {
+    let history: History<String, u64> = match History::from_json_str(json) {
+        Ok(history) => history,
+        Err(e) => return error(&e.to_string()),
+    };
+
+    let (holds, witness) = match level {
+        "ser" => ordered(history.ser_order()),
+        "si" => ordered(history.si_order()),
+        "gsi" => ordered(history.gsi_order()),
+        "prefix" => ordered(history.prefix_order()),
+        "causal" => (history.causal_check(), None),
+        "cs" => (history.cursor_stability_check(), None),
+        "mav" => (history.mav_check(), None),
+        "rc" => (history.read_committed_check(), None),
+        "ru" => (history.read_uncommitted_check(), None),
+        _ => return error(&alloc::format!("unknown level {:?}", level)),
+    };
+
+    let mut verdict = json!({ "level": level, "holds": holds });
+    if let Some(witness) = witness {
+        verdict["witness"] = witness;
+    }
+    if !holds {
+        if let Some(anomaly) = history.classify_anomaly() {
+            verdict["anomaly"] = Value::from(anomaly.to_string());
+        }
+    }
+
+    verdict.to_string()
+}
+
+fn ordered(order: Option<alloc::vec::Vec<(usize, usize)>>) -> (bool, Option<Value>) {
+    match order {
+        Some(order) => (true, Some(json!(order))),
+        None => (false, None),
+    }
+}
+
+fn error(message: &str) -> String {
+    json!({ "error": message }).to_string()
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "std")]
+
+use ergosum::ser_checker::SerChecker;
+use ergosum::transaction::{History, Op, Transaction};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// every client reads what the next one wrote in the same round, so most
+// candidates of the search are blocked by a read
+#[test]
+fn string_keyed_search() {
+    let clients = 6;
+    let rounds = 40;
+
+    let mut transactions = Vec::new();
+    for c in 0..clients {
+        let mut client = Vec::new();
+        for i in 1..=rounds {
+            let mut transaction = Transaction::builder();
+            if c + 1 < clients {
+                transaction.get(format!("key-{}", c + 1), i);
+            }
+            client.push(transaction.set(format!("key-{}", c), i).build());
+        }
+        transactions.push(client);
+    }
+    let mut checker: SerChecker<String, usize> = SerChecker::new(transactions);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    assert!(checker.check());
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    // the search used to clone keys and values for every blocked candidate,
+    // taking over 8000 allocations
+    println!("{} allocations", allocations);
+    assert!(allocations < 5000);
+}
+
+// the same search with string values, which the search states hold as ids
+#[test]
+fn string_valued_search() {
+    let clients = 6;
+    let rounds = 40;
+
+    let mut transactions = Vec::new();
+    for c in 0..clients {
+        let mut client = Vec::new();
+        for i in 1..=rounds {
+            let mut transaction = Transaction::builder();
+            if c + 1 < clients {
+                transaction.get(format!("key-{}", c + 1), format!("value-{}-{}", c + 1, i));
+            }
+            client.push(transaction.set(format!("key-{}", c), format!("value-{}-{}", c, i)).build());
+        }
+        transactions.push(client);
+    }
+    let mut checker: SerChecker<String, String> = SerChecker::new(transactions);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    assert!(checker.check());
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    // cloning the values of every key into every search state took
+    // almost 7000 allocations
+    println!("{} allocations", allocations);
+    assert!(allocations < 6000);
+}
+
+// checking a history builds the checker from the borrowed transactions,
+// instead of cloning the history to append the init transaction to it
+#[test]
+fn borrowed_history() {
+    let clients = 6;
+    let rounds = 40;
+
+    let mut transactions = Vec::new();
+    for c in 0..clients {
+        let mut client = Vec::new();
+        for i in 1..=rounds {
+            let mut transaction = Transaction::builder();
+            if c + 1 < clients {
+                transaction.get(format!("key-{}", c + 1), i);
+            }
+            client.push(transaction.set(format!("key-{}", c), i).build());
+        }
+        transactions.push(client);
+    }
+    let history: History<String, usize> = History::new(transactions);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    assert!(history.ser_check());
+    let borrowed = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let mut transactions = history.transactions.clone();
+    let init = (0..clients).map(|c| Op::set(format!("key-{}", c), 0)).collect();
+    transactions.push(vec![Transaction { ops: init, aborted: false, time: None, interval: None }]);
+    let mut checker = SerChecker::new(transactions);
+    checker.commit(clients);
+    assert!(checker.check_with_witness().is_ok());
+    let cloned = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    // cloning allocates the ops of every transaction of the history again
+    println!("{} allocations, {} with a clone", borrowed, cloned);
+    assert!(borrowed + 2 * clients * rounds < cloned);
+}
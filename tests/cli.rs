@@ -0,0 +1,91 @@
+#![cfg(feature = "std")]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const WRITE_SKEW: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/write_skew.txt");
+
+fn ergosum() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_ergosum"))
+}
+
+#[test]
+fn every_level() {
+    let output = ergosum().arg(WRITE_SKEW).output().unwrap();
+
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "ser: fail\nsi: pass\ngsi: pass\nprefix: pass\ncausal: pass\ncs: pass\nmav: pass\nrc: pass\nru: pass\n"
+    );
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn single_level() {
+    let output = ergosum().args(["--level", "si", WRITE_SKEW]).output().unwrap();
+
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "si: pass\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn from_stdin() {
+    let mut child = ergosum()
+        .args(["--level", "ser"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"0: set(x)=1 abort\n0: cas(x)=0->2 delete(y)\n1: get(x)=2 read(l)=[] absent(y)\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "ser: pass\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn invalid_history() {
+    let output = ergosum().args(["--level", "ser", "-"]).stdin(Stdio::piped()).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+
+    let mut child = ergosum().stdin(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(b"0: put(x)=1\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "ergosum: line 1: invalid operation \"put(x)=1\"\n"
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json() {
+    use ergosum::transaction::{History, Transaction};
+
+    let history: History<String, u64> = History::builder()
+        .client()
+        .transaction(Transaction::builder().set("x".to_string(), 1).build())
+        .client()
+        .transaction(Transaction::builder().get("x".to_string(), 1).build())
+        .build();
+    let json = serde_json::to_string(&history).unwrap();
+
+    let mut child = ergosum()
+        .args(["--level", "ser"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(json.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "ser: pass\n");
+    assert_eq!(output.status.code(), Some(0));
+}
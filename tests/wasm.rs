@@ -0,0 +1,34 @@
+#![cfg(feature = "wasm")]
+
+use ergosum::wasm::check_history_json;
+
+const WRITE_SKEW: &str = r#"{"transactions": [
+    [{"ops": [{"Get": {"key": "x", "val": 0}}, {"Get": {"key": "y", "val": 0}}, {"Set": {"key": "x", "val": 1}}], "aborted": false}],
+    [{"ops": [{"Get": {"key": "x", "val": 0}}, {"Get": {"key": "y", "val": 0}}, {"Set": {"key": "y", "val": 1}}], "aborted": false}]
+]}"#;
+
+#[test]
+fn failing_level() {
+    assert_eq!(
+        check_history_json(WRITE_SKEW, "ser"),
+        r#"{"anomaly":"write skew","holds":false,"level":"ser"}"#
+    );
+}
+
+#[test]
+fn witness() {
+    assert_eq!(
+        check_history_json(WRITE_SKEW, "si"),
+        r#"{"holds":true,"level":"si","witness":[[0,0],[1,0]]}"#
+    );
+    assert_eq!(check_history_json(WRITE_SKEW, "rc"), r#"{"holds":true,"level":"rc"}"#);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(check_history_json(WRITE_SKEW, "strict"), r#"{"error":"unknown level \"strict\""}"#);
+
+    let verdict: serde_json::Value = serde_json::from_str(&check_history_json("[", "ser")).unwrap();
+    assert!(verdict["error"].is_string());
+    assert_eq!(verdict.as_object().unwrap().len(), 1);
+}